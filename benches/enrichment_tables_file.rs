@@ -1,11 +1,14 @@
-use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
-use std::collections::BTreeMap;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use std::collections::{BTreeMap, HashMap};
 use vector::enrichment_tables::{file::File, Condition, Table};
+use vector_core::enrichment::{Glob, TableRegistry};
+use vrl::enrichment::{TableSearch, TableSetup};
+use vrl::Value;
 
 criterion_group!(
     name = benches;
     config = Criterion::default().noise_threshold(0.02).sample_size(10);
-    targets = benchmark_enrichment_tables_file
+    targets = benchmark_enrichment_tables_file, benchmark_enrichment_tables_registry
 );
 criterion_main!(benches);
 
@@ -47,7 +50,7 @@ fn benchmark_enrichment_tables_file(c: &mut Criterion) {
             .map(|idx| {
                 (
                     format!("field-{}", idx),
-                    format!("data-{}-{}", idx, size - 1),
+                    Value::from(format!("data-{}-{}", idx, size - 1)),
                 )
             })
             .collect::<BTreeMap<_, _>>();
@@ -60,7 +63,7 @@ fn benchmark_enrichment_tables_file(c: &mut Criterion) {
         b.iter_batched(
             || (&file, &condition, expected.clone()),
             |(file, condition, expected)| {
-                assert_eq!(Ok(expected), file.find_table_row(condition, None))
+                assert_eq!(Ok(expected), file.find_table_row(condition, None, None))
             },
             BatchSize::SmallInput,
         );
@@ -71,7 +74,10 @@ fn benchmark_enrichment_tables_file(c: &mut Criterion) {
         b.iter_batched(
             || (&file, index, &condition, expected.clone()),
             |(file, index, condition, expected)| {
-                assert_eq!(Ok(expected), file.find_table_row(condition, Some(index)))
+                assert_eq!(
+                    Ok(expected),
+                    file.find_table_row(condition, None, Some(index))
+                )
             },
             BatchSize::SmallInput,
         );
@@ -82,7 +88,7 @@ fn benchmark_enrichment_tables_file(c: &mut Criterion) {
         b.iter_batched(
             || (&file, &condition, expected.clone()),
             |(file, condition, expected)| {
-                assert_eq!(Ok(expected), file.find_table_row(condition, None))
+                assert_eq!(Ok(expected), file.find_table_row(condition, None, None))
             },
             BatchSize::SmallInput,
         );
@@ -93,7 +99,10 @@ fn benchmark_enrichment_tables_file(c: &mut Criterion) {
         b.iter_batched(
             || (&file, index, &condition, expected.clone()),
             |(file, index, condition, expected)| {
-                assert_eq!(Ok(expected), file.find_table_row(condition, Some(index)))
+                assert_eq!(
+                    Ok(expected),
+                    file.find_table_row(condition, None, Some(index))
+                )
             },
             BatchSize::SmallInput,
         );
@@ -104,7 +113,7 @@ fn benchmark_enrichment_tables_file(c: &mut Criterion) {
         b.iter_batched(
             || (&file, &condition, expected.clone()),
             |(file, condition, expected)| {
-                assert_eq!(Ok(expected), file.find_table_row(condition, None))
+                assert_eq!(Ok(expected), file.find_table_row(condition, None, None))
             },
             BatchSize::SmallInput,
         );
@@ -115,9 +124,107 @@ fn benchmark_enrichment_tables_file(c: &mut Criterion) {
         b.iter_batched(
             || (&file, index, &condition, expected.clone()),
             |(file, index, condition, expected)| {
-                assert_eq!(Ok(expected), file.find_table_row(condition, Some(index)))
+                assert_eq!(
+                    Ok(expected),
+                    file.find_table_row(condition, None, Some(index))
+                )
             },
             BatchSize::SmallInput,
         );
     });
 }
+
+/// Searches through the registry, as VRL does, to compare an indexed lookup with the scans that
+/// unindexed and glob conditions fall back to.
+fn benchmark_enrichment_tables_registry(c: &mut Criterion) {
+    let mut group = c.benchmark_group("enrichment_tables_registry");
+
+    let setup = |size: usize| {
+        let file = File::new(
+            (0..size)
+                .map(|row| {
+                    (0..10)
+                        .map(|col| format!("data-{}-{}", col, row))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>(),
+            (0..10)
+                .map(|header| format!("field-{}", header))
+                .collect::<Vec<_>>(),
+        );
+
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("file".to_string(), Box::new(file));
+
+        let mut registry = TableRegistry::default();
+        registry.load(tables);
+        let index = registry.add_index("file", &["field-0"]).unwrap();
+        registry.finish_load();
+
+        let equals = vec![Condition::Equals {
+            field: "field-0",
+            value: format!("data-0-{}", size - 1),
+        }];
+        let glob = vec![Condition::Glob {
+            field: "field-9",
+            pattern: Glob::new(&format!("*-9-{}", size - 1)),
+        }];
+
+        let result = (0..10)
+            .map(|idx| {
+                (
+                    format!("field-{}", idx),
+                    Value::from(format!("data-{}-{}", idx, size - 1)),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        (registry.as_readonly(), index, equals, glob, result)
+    };
+
+    for size in [10, 1_000, 1_000_000].iter().copied() {
+        // Each benchmark builds its own table, so only the tables of the benchmarks being run are
+        // held in memory.
+        group.bench_with_input(BenchmarkId::new("indexed", size), &size, |b, size| {
+            let (search, index, equals, _glob, expected) = setup(*size);
+            b.iter_batched(
+                || (&equals, expected.clone()),
+                |(condition, expected)| {
+                    assert_eq!(
+                        Ok(expected),
+                        search.find_table_row("file", condition, None, Some(index))
+                    )
+                },
+                BatchSize::SmallInput,
+            );
+        });
+
+        group.bench_with_input(BenchmarkId::new("scan", size), &size, |b, size| {
+            let (search, _index, equals, _glob, expected) = setup(*size);
+            b.iter_batched(
+                || (&equals, expected.clone()),
+                |(condition, expected)| {
+                    assert_eq!(
+                        Ok(expected),
+                        search.find_table_row("file", condition, None, None)
+                    )
+                },
+                BatchSize::SmallInput,
+            );
+        });
+
+        group.bench_with_input(BenchmarkId::new("glob_scan", size), &size, |b, size| {
+            let (search, _index, _equals, glob, expected) = setup(*size);
+            b.iter_batched(
+                || (&glob, expected.clone()),
+                |(condition, expected)| {
+                    assert_eq!(
+                        Ok(expected),
+                        search.find_table_row("file", condition, None, None)
+                    )
+                },
+                BatchSize::SmallInput,
+            );
+        });
+    }
+}
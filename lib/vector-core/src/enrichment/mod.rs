@@ -3,23 +3,27 @@ pub mod tables;
 use std::collections::BTreeMap;
 
 use dyn_clone::DynClone;
+use serde::de::DeserializeOwned;
+use vrl_core::Value;
 
-pub use tables::{TableRegistry, TableSearch};
-pub use vrl_core::enrichment::{Condition, IndexHandle};
+pub use tables::{TableRegistry, TableSearch, TableSnapshot};
+pub use vrl_core::enrichment::{Condition, Direction, Glob, IndexHandle};
 
 /// Enrichment tables represent additional data sources that can be used to enrich the event data
 /// passing through Vector.
 pub trait Table: DynClone {
     /// Search the enrichment table data with the given condition.
     /// All conditions must match (AND).
+    /// If `select` is given, only those columns are returned.
     ///
     /// # Errors
-    /// Errors if no rows, or more than 1 row is found.
+    /// Errors if no rows, or more than 1 row is found, or a selected column is not in the table.
     fn find_table_row<'a>(
         &self,
         condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
         index: Option<IndexHandle>,
-    ) -> Result<BTreeMap<String, String>, String>;
+    ) -> Result<BTreeMap<String, Value>, String>;
 
     /// Hints to the enrichment table what data is going to be searched to allow it to index the
     /// data in advance.
@@ -27,6 +31,195 @@ pub trait Table: DynClone {
     /// # Errors
     /// Errors if the fields are not in the table.
     fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String>;
+
+    /// Hints to the enrichment table that the field is going to be searched with an operator,
+    /// such as `nearest` or `contains`, so tables that can index operators build what they need
+    /// for them only when it is asked for. Tables that can't add an index for equality.
+    ///
+    /// # Errors
+    /// Errors if the field is not in the table.
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        self.add_index(&[field])
+    }
+
+    /// Returns the names of the columns that rows returned by this table can contain.
+    fn columns(&self) -> Vec<String>;
+
+    /// Writes the row, replacing the row with the same key, for tables that can be written to
+    /// whilst serving lookups.
+    ///
+    /// # Errors
+    /// Errors if the table can't be written to, or the row has a column not in the table.
+    fn upsert_row(&self, _row: BTreeMap<String, Value>) -> Result<(), String> {
+        Err("table can't be written to".to_string())
+    }
+
+    /// Searches for the rows matching the condition as `find_table_row` does, but returns how the
+    /// search was made rather than the rows, for finding out why lookups are slow. VRL reports
+    /// this through `explain_enrichment_table_lookup`. Returns `None` if the table can't explain
+    /// its searches.
+    fn explain<'a>(
+        &self,
+        _condition: &'a [Condition<'a>],
+        _index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        None
+    }
+
+    /// Returns the approximate memory used by each index in bytes, in the order they were added.
+    /// Tables that don't build indexes return none.
+    fn index_bytes(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// Iterates over all the rows loaded into the table, for exporting or debugging the data.
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_>;
 }
 
 dyn_clone::clone_trait_object!(Table);
+
+/// Whether an index was added for equality, by `Table::add_index`, or for an operator, by
+/// `Table::add_operator_index`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexKind {
+    Equals,
+    Operator,
+}
+
+impl IndexKind {
+    /// Adds an index of this kind on the fields to the table. Operator indexes are on a single
+    /// field.
+    ///
+    /// # Errors
+    /// Errors if the fields are not in the table.
+    pub fn add_to(self, table: &mut dyn Table, fields: &[&str]) -> Result<IndexHandle, String> {
+        match (self, fields) {
+            (IndexKind::Operator, [field]) => table.add_operator_index(field),
+            _ => table.add_index(fields),
+        }
+    }
+}
+
+/// How a table searched for the rows matching a condition, see `Table::explain`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explain {
+    /// The fields of the index that was searched, or `None` if no index was used.
+    pub index: Option<Vec<String>>,
+    pub search: Search,
+    /// The number of rows that were checked against the condition.
+    pub rows_examined: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Search {
+    /// Every row was checked against the condition.
+    Scan,
+    /// The rows were found by hashing the values given for every indexed column.
+    Hash,
+    /// The rows were found amongst the rows sorted by the leading indexed columns.
+    Prefix,
+    /// The rows were found by searching the numbers of the indexed column in order.
+    Nearest,
+    /// The rows were found amongst the members of the sets in the indexed column.
+    Members,
+    /// The rows were found amongst the networks of each prefix length containing the address.
+    Networks,
+}
+
+impl Search {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Scan => "scan",
+            Self::Hash => "hash",
+            Self::Prefix => "prefix",
+            Self::Nearest => "nearest",
+            Self::Members => "members",
+            Self::Networks => "networks",
+        }
+    }
+}
+
+impl From<Explain> for BTreeMap<String, Value> {
+    /// Gives the fields of the index as an array, or null if no index was used.
+    fn from(explain: Explain) -> Self {
+        let index = match explain.index {
+            Some(fields) => Value::from(fields),
+            None => Value::Null,
+        };
+
+        vec![
+            ("index".to_string(), index),
+            ("search".to_string(), Value::from(explain.search.as_str())),
+            (
+                "rows_examined".to_string(),
+                Value::Integer(explain.rows_examined as i64),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+/// Deserializes a row returned by a table into a type of the embedder's, such as a struct with a
+/// field for each column, so the columns aren't looked up by name.
+///
+/// # Errors
+/// Errors if a column the type requires is missing from the row, or a column can't be
+/// deserialized into the type of its field, naming the column.
+pub fn deserialize_row<T: DeserializeOwned>(row: BTreeMap<String, Value>) -> Result<T, String> {
+    let row = serde_json::to_value(row).map_err(|error| error.to_string())?;
+
+    serde_path_to_error::deserialize(row).map_err(|error| {
+        let column = error.path().to_string();
+        match column.as_str() {
+            "." => format!("invalid row: {}", error.inner()),
+            _ => format!("invalid column '{}': {}", column, error.inner()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use shared::btreemap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Host {
+        name: String,
+        port: u16,
+        owner: Option<String>,
+    }
+
+    #[test]
+    fn deserializes_row() {
+        assert_eq!(
+            Ok(Host {
+                name: "db".to_string(),
+                port: 5432,
+                owner: None,
+            }),
+            deserialize_row(btreemap! {
+                "name" => "db",
+                "port" => 5432,
+                "owner" => Value::Null,
+                "region" => "eu",
+            })
+        );
+    }
+
+    #[test]
+    fn reports_mismatched_columns() {
+        assert_eq!(
+            Err::<Host, _>("invalid row: missing field `port`".to_string()),
+            deserialize_row(btreemap! { "name" => "db" })
+        );
+        assert_eq!(
+            Err::<Host, _>(
+                r#"invalid column 'port': invalid type: string "postgres", expected u16"#
+                    .to_string()
+            ),
+            deserialize_row(btreemap! { "name" => "db", "port" => "postgres" })
+        );
+    }
+}
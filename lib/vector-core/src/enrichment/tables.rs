@@ -24,7 +24,19 @@
 //! needs access to this can call `TableRegistry::as_readonly`. This returns a cheaply clonable struct that
 //! implements `vrl:EnrichmentTableSearch` through with the enrichment tables can be searched.
 //!
-use super::{IndexHandle, Table};
+//! ## Reloading
+//!
+//! When the config is reloaded the tables go back through the writing stage. Tables that haven't
+//! changed keep their data, and transforms asking for an index they already have are given the
+//! existing handle. Tables whose config has changed are built again and given to
+//! `TableRegistry::reload`, which rebuilds the indexes of the existing table on the new copy so the
+//! `IndexHandle`s held by running transforms stay valid. Tables removed from the config are
+//! dropped with `TableRegistry::remove`.
+//!
+//! Lookups that need to see the same data across a reload can search a `TableSnapshot` instead,
+//! which keeps the tables that were being served when it was taken.
+//!
+use super::{Explain, IndexHandle, IndexKind, Table};
 use arc_swap::ArcSwap;
 use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex};
@@ -33,6 +45,9 @@ use std::sync::{Arc, Mutex};
 pub struct TableRegistry {
     loading: Arc<Mutex<Option<HashMap<String, Box<dyn Table + Send + Sync>>>>>,
     tables: Arc<ArcSwap<Option<HashMap<String, Box<dyn Table + Send + Sync>>>>>,
+    /// The fields, kind and handle of every index added to each table, in the order they were
+    /// added.
+    indexes: Arc<Mutex<HashMap<String, Vec<(Vec<String>, IndexKind, IndexHandle)>>>>,
 }
 
 impl TableRegistry {
@@ -56,8 +71,8 @@ impl TableRegistry {
     /// Once loading is complete, the data is swapped out of `loading` and we return to a single
     /// copy of the tables.
     ///
-    /// TODO This function currently does nothing to reload the the underlying data should it have
-    /// changed in the enrichment source.
+    /// This function does nothing to reload the underlying data should it have changed in the
+    /// enrichment source, see `reload` for that.
     ///
     /// # Panics
     ///
@@ -91,11 +106,198 @@ impl TableRegistry {
         let tables = tables_lock.take();
         self.tables.swap(Arc::new(tables));
     }
+
+    /// Replaces a loaded table with a freshly built copy of it, for example one that has re-read
+    /// its data from the enrichment source. This must be called in the writing stage, after
+    /// `load`, and the new copy is served once `finish_load` is called.
+    ///
+    /// The indexes that were added to the existing table are added to the new copy in the same
+    /// order, so that the handles given out for them still refer to the same fields.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the table isn't loaded, or an index can't be added to the new copy, or is given a
+    /// different handle by it. The existing table is kept.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    pub fn reload(
+        &self,
+        name: &str,
+        mut table: Box<dyn Table + Send + Sync>,
+    ) -> Result<(), String> {
+        match *self.loading.lock().unwrap() {
+            Some(ref tables) if tables.contains_key(name) => {}
+            Some(ref tables) => return Err(not_loaded(name, tables)),
+            None => return Err(format!("table '{}' not loaded", name)),
+        }
+
+        // The locks aren't held whilst adding the indexes, since tables joining other tables of
+        // the registry add indexes to them.
+        let indexes = self.indexes.lock().unwrap().get(name).cloned();
+        for (fields, kind, handle) in indexes.unwrap_or_default() {
+            let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+            if kind.add_to(&mut *table, &fields)? != handle {
+                return Err(format!(
+                    "index on {} of table '{}' changed when reloading",
+                    fields.join(", "),
+                    name
+                ));
+            }
+        }
+
+        if let Some(ref mut tables) = *self.loading.lock().unwrap() {
+            tables.insert(name.to_string(), table);
+        }
+        Ok(())
+    }
+
+    /// Drops a table that has been removed from the config, and forgets its indexes so a table
+    /// added later under the same name starts without them. This must be called in the writing
+    /// stage, after `load`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    pub fn remove(&self, name: &str) {
+        if let Some(ref mut tables) = *self.loading.lock().unwrap() {
+            tables.remove(name);
+        }
+        self.indexes.lock().unwrap().remove(name);
+    }
+
+    /// Returns the columns of the given table, whilst it is being loaded or once it is being
+    /// served.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the table isn't loaded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    pub fn columns(&self, table: &str) -> Result<Vec<String>, String> {
+        if let Some(ref tables) = *self.loading.lock().unwrap() {
+            return match tables.get(table) {
+                Some(loaded) => Ok(loaded.columns()),
+                None => Err(not_loaded(table, tables)),
+            };
+        }
+
+        match &**self.tables.load() {
+            Some(tables) => match tables.get(table) {
+                Some(served) => Ok(served.columns()),
+                None => Err(not_loaded(table, tables)),
+            },
+            None => Err(format!("table '{}' not loaded", table)),
+        }
+    }
+
+    /// Returns a cheaply clonable struct for searching the tables, see `TableSearch`.
+    pub fn search(&self) -> TableSearch {
+        TableSearch(self.tables.clone())
+    }
+
+    /// Returns a snapshot of the tables as they are now, see `TableSearch::snapshot`. This must be
+    /// called in the reading stage, after `finish_load`.
+    pub fn snapshot(&self) -> TableSnapshot {
+        TableSnapshot(self.tables.load_full())
+    }
+
+    /// Returns the fields of each index of each table with the approximate memory it uses in
+    /// bytes. This must be called in the reading stage, after `finish_load`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    pub fn index_bytes(&self) -> BTreeMap<String, Vec<(Vec<String>, usize)>> {
+        let tables = self.tables.load();
+        let indexes = self.indexes.lock().unwrap();
+
+        match **tables {
+            Some(ref tables) => tables
+                .iter()
+                .map(|(name, table)| {
+                    let fields = indexes
+                        .get(name)
+                        .map(|indexes| {
+                            indexes
+                                .iter()
+                                .map(|(fields, _, _)| fields.clone())
+                                .collect()
+                        })
+                        .unwrap_or_else(Vec::new);
+                    (
+                        name.clone(),
+                        fields.into_iter().zip(table.index_bytes()).collect(),
+                    )
+                })
+                .collect(),
+            None => BTreeMap::new(),
+        }
+    }
+
+    /// Adds an index of the given kind to the table, or returns the handle of the index of the
+    /// same kind already added on the same fields.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    #[cfg(feature = "vrl")]
+    fn add_index_of_kind(
+        &self,
+        table: &str,
+        fields: &[&str],
+        kind: IndexKind,
+    ) -> Result<IndexHandle, String> {
+        let existing = self.indexes.lock().unwrap().get(table).and_then(|indexes| {
+            indexes
+                .iter()
+                .find(|(existing, existing_kind, _)| {
+                    *existing_kind == kind
+                        && existing
+                            .iter()
+                            .map(String::as_str)
+                            .eq(fields.iter().copied())
+                })
+                .map(|(_, _, handle)| *handle)
+        });
+
+        // The table is taken out whilst the index is added, so the locks aren't held by tables
+        // joining other tables of the registry that add indexes to them.
+        let mut loaded = match *self.loading.lock().unwrap() {
+            None => return Err("finish_load has been called".to_string()),
+            Some(ref mut tables) => match (tables.contains_key(table), existing) {
+                (false, _) => return Err(not_loaded(table, tables)),
+                (true, Some(handle)) => return Ok(handle),
+                (true, None) => tables.remove(table).expect("table is loaded"),
+            },
+        };
+
+        let added = kind.add_to(&mut *loaded, fields);
+        if let Some(ref mut tables) = *self.loading.lock().unwrap() {
+            tables.insert(table.to_string(), loaded);
+        }
+
+        let handle = added?;
+        self.indexes
+            .lock()
+            .unwrap()
+            .entry(table.to_string())
+            .or_insert_with(Vec::new)
+            .push((
+                fields.iter().map(|field| (*field).to_string()).collect(),
+                kind,
+                handle,
+            ));
+        Ok(handle)
+    }
 }
 
 impl std::fmt::Debug for TableRegistry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        fmt_enrichment_table(f, "TableRegistry", &self.tables)
+        fmt_enrichment_table(f, "TableRegistry", &self.tables.load())
     }
 }
 
@@ -115,32 +317,37 @@ impl vrl_core::enrichment::TableSetup for TableRegistry {
         }
     }
 
-    /// Adds an index to the given Enrichment Table.
+    /// Adds an index to the given Enrichment Table, or returns the handle of the index already
+    /// added on the same fields.
     /// If we are in the reading stage, this function will error.
     ///
     /// # Panics
     ///
     /// Panics if the Mutex is poisoned.
     fn add_index(&mut self, table: &str, fields: &[&str]) -> Result<IndexHandle, String> {
-        let mut locked = self.loading.lock().unwrap();
+        self.add_index_of_kind(table, fields, IndexKind::Equals)
+    }
 
-        match *locked {
-            None => Err("finish_load has been called".to_string()),
-            Some(ref mut tables) => match tables.get_mut(table) {
-                None => Err(format!("table '{}' not loaded", table)),
-                Some(table) => table.add_index(fields),
-            },
-        }
+    /// Adds an index for searching the field of the given Enrichment Table with an operator, or
+    /// returns the handle of the operator index already added on the field.
+    /// If we are in the reading stage, this function will error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    fn add_operator_index(&mut self, table: &str, field: &str) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(table, &[field], IndexKind::Operator)
     }
 
     /// Returns a cheaply clonable struct through that provides lock free read access to the
     /// enrichment tables.
     fn as_readonly(&self) -> Box<dyn vrl_core::enrichment::TableSearch + Send + Sync> {
-        Box::new(TableSearch(self.tables.clone()))
+        Box::new(self.search())
     }
 }
 
-/// Provides read only access to the enrichment tables via the `vrl::EnrichmentTableSearch` trait.
+/// Provides read only access to the enrichment tables via the `vrl::EnrichmentTableSearch` trait,
+/// other than upserting rows into the tables that can be written to whilst serving lookups.
 /// Cloning this object is designed to be cheap. The underlying data will be shared by all clones.
 #[derive(Clone, Default)]
 pub struct TableSearch(Arc<ArcSwap<Option<HashMap<String, Box<dyn Table + Send + Sync>>>>>);
@@ -152,40 +359,184 @@ impl vrl_core::enrichment::TableSearch for TableSearch {
         &self,
         table: &str,
         condition: &'a [vrl_core::enrichment::Condition<'a>],
+        select: Option<&[String]>,
         index: Option<IndexHandle>,
     ) -> Result<BTreeMap<String, vrl_core::Value>, String> {
-        let tables = self.0.load();
-        if let Some(ref tables) = **tables {
-            match tables.get(table) {
-                None => Err(format!("table {} not loaded", table)),
-                Some(table) => table.find_table_row(condition, index).map(|table| {
-                    table
-                        .iter()
-                        .map(|(key, value)| (key.to_string(), value.as_str().into()))
-                        .collect()
-                }),
-            }
-        } else {
-            Err("finish_load not called".to_string())
+        find_table_row(&self.0.load(), table, condition, select, index)
+    }
+
+    /// Writes the row to the given table, for tables that can be written to whilst serving
+    /// lookups. If we are in the writing stage, this function will return an error.
+    fn upsert_table_row(
+        &self,
+        table: &str,
+        row: BTreeMap<String, vrl_core::Value>,
+    ) -> Result<(), String> {
+        upsert_table_row(&self.0.load(), table, row)
+    }
+
+    /// Explains how the given table would search for the rows matching the condition. If we are
+    /// in the writing stage, this function will return an error.
+    fn explain_table_search<'a>(
+        &'a self,
+        table: &str,
+        condition: &'a [vrl_core::enrichment::Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Result<Option<BTreeMap<String, vrl_core::Value>>, String> {
+        Ok(self.explain(table, condition, index)?.map(Into::into))
+    }
+}
+
+impl TableSearch {
+    /// Returns a snapshot of the tables as they are now, so that a batch of related lookups all
+    /// search the same data even if the tables are reloaded part way through.
+    pub fn snapshot(&self) -> TableSnapshot {
+        TableSnapshot(self.0.load_full())
+    }
+
+    /// Explains how the given table would search for the rows matching the condition, see
+    /// `Table::explain`. If we are in the writing stage, this function will return an error.
+    pub fn explain<'a>(
+        &self,
+        table: &str,
+        condition: &'a [vrl_core::enrichment::Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Result<Option<Explain>, String> {
+        explain(&self.0.load(), table, condition, index)
+    }
+
+    /// Returns all the rows of the given table, see `Table::iter_rows`. If we are in the writing
+    /// stage, this function will return an error.
+    pub fn rows(&self, table: &str) -> Result<Vec<BTreeMap<String, vrl_core::Value>>, String> {
+        match &**self.0.load() {
+            Some(tables) => match tables.get(table) {
+                None => Err(not_loaded(table, tables)),
+                Some(table) => Ok(table.iter_rows().collect()),
+            },
+            None => Err("finish_load not called".to_string()),
         }
     }
 }
 
 impl std::fmt::Debug for TableSearch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        fmt_enrichment_table(f, "EnrichmentTableSearch", &self.0)
+        fmt_enrichment_table(f, "EnrichmentTableSearch", &self.0.load())
     }
 }
 
+/// The enrichment tables at the time `TableSearch::snapshot` was called. Reloads swap new tables
+/// into the `TableSearch` without changing those of existing snapshots, which are kept in memory
+/// until the last clone of the snapshot is dropped.
+///
+/// Tables that refresh their own data, rather than being reloaded with the config, share it with
+/// the snapshot so lookups on the snapshot see the refreshed data.
+#[derive(Clone)]
+pub struct TableSnapshot(Arc<Option<HashMap<String, Box<dyn Table + Send + Sync>>>>);
+
+impl vrl_core::enrichment::TableSearch for TableSnapshot {
+    fn find_table_row<'a>(
+        &self,
+        table: &str,
+        condition: &'a [vrl_core::enrichment::Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, vrl_core::Value>, String> {
+        find_table_row(&self.0, table, condition, select, index)
+    }
+
+    fn upsert_table_row(
+        &self,
+        table: &str,
+        row: BTreeMap<String, vrl_core::Value>,
+    ) -> Result<(), String> {
+        upsert_table_row(&self.0, table, row)
+    }
+
+    fn explain_table_search<'a>(
+        &'a self,
+        table: &str,
+        condition: &'a [vrl_core::enrichment::Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Result<Option<BTreeMap<String, vrl_core::Value>>, String> {
+        Ok(explain(&self.0, table, condition, index)?.map(Into::into))
+    }
+}
+
+impl std::fmt::Debug for TableSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_enrichment_table(f, "EnrichmentTableSnapshot", &self.0)
+    }
+}
+
+/// Searches the named table, erroring if the tables are still being written.
+fn find_table_row<'a>(
+    tables: &Option<HashMap<String, Box<dyn Table + Send + Sync>>>,
+    table: &str,
+    condition: &'a [vrl_core::enrichment::Condition<'a>],
+    select: Option<&[String]>,
+    index: Option<IndexHandle>,
+) -> Result<BTreeMap<String, vrl_core::Value>, String> {
+    match tables {
+        Some(tables) => match tables.get(table) {
+            None => Err(not_loaded(table, tables)),
+            Some(table) => table.find_table_row(condition, select, index),
+        },
+        None => Err("finish_load not called".to_string()),
+    }
+}
+
+/// Writes the row to the named table, erroring if the tables are still being written.
+fn upsert_table_row(
+    tables: &Option<HashMap<String, Box<dyn Table + Send + Sync>>>,
+    table: &str,
+    row: BTreeMap<String, vrl_core::Value>,
+) -> Result<(), String> {
+    match tables {
+        Some(tables) => match tables.get(table) {
+            None => Err(not_loaded(table, tables)),
+            Some(table) => table.upsert_row(row),
+        },
+        None => Err("finish_load not called".to_string()),
+    }
+}
+
+/// Explains how the named table would search, erroring if the tables are still being written.
+fn explain<'a>(
+    tables: &Option<HashMap<String, Box<dyn Table + Send + Sync>>>,
+    table: &str,
+    condition: &'a [vrl_core::enrichment::Condition<'a>],
+    index: Option<IndexHandle>,
+) -> Result<Option<Explain>, String> {
+    match tables {
+        Some(tables) => match tables.get(table) {
+            None => Err(not_loaded(table, tables)),
+            Some(table) => Ok(table.explain(condition, index)),
+        },
+        None => Err("finish_load not called".to_string()),
+    }
+}
+
+/// The error for a table that isn't loaded, listing those that are since the name is most likely
+/// misspelt or the table has been removed from the config.
+fn not_loaded(table: &str, tables: &HashMap<String, Box<dyn Table + Send + Sync>>) -> String {
+    let mut available = tables.keys().map(String::as_str).collect::<Vec<_>>();
+    available.sort_unstable();
+
+    format!(
+        "table '{}' not loaded, available tables are: {}",
+        table,
+        available.join(", ")
+    )
+}
+
 /// Provide some fairly rudimentary debug output for enrichment tables.
 fn fmt_enrichment_table(
     f: &mut std::fmt::Formatter<'_>,
     name: &'static str,
-    tables: &Arc<ArcSwap<Option<HashMap<String, Box<dyn Table + Send + Sync>>>>>,
+    tables: &Option<HashMap<String, Box<dyn Table + Send + Sync>>>,
 ) -> std::fmt::Result {
-    let tables = tables.load();
-    match **tables {
-        Some(ref tables) => {
+    match tables {
+        Some(tables) => {
             let mut tables = tables.iter().fold(String::from("("), |mut s, (key, _)| {
                 s.push_str(key);
                 s.push_str(", ");
@@ -204,13 +555,15 @@ fn fmt_enrichment_table(
 #[cfg(all(feature = "vrl", test))]
 mod tests {
     use super::*;
+    use crate::enrichment::Search;
     use shared::btreemap;
     use std::sync::{Arc, Mutex};
     use vrl_core::enrichment::{Condition, TableSetup};
+    use vrl_core::Value;
 
     #[derive(Debug, Clone)]
     struct DummyEnrichmentTable {
-        data: BTreeMap<String, String>,
+        data: BTreeMap<String, Value>,
         indexes: Arc<Mutex<Vec<Vec<String>>>>,
     }
 
@@ -233,16 +586,50 @@ mod tests {
         fn find_table_row(
             &self,
             _condition: &[Condition],
+            _select: Option<&[String]>,
             _index: Option<vrl_core::enrichment::IndexHandle>,
-        ) -> Result<BTreeMap<String, String>, String> {
+        ) -> Result<BTreeMap<String, Value>, String> {
             Ok(self.data.clone())
         }
 
+        fn explain(&self, _condition: &[Condition], index: Option<IndexHandle>) -> Option<Explain> {
+            let indexes = self.indexes.lock().unwrap();
+            Some(match index {
+                Some(IndexHandle(handle)) => Explain {
+                    index: Some(indexes[handle].clone()),
+                    search: Search::Hash,
+                    rows_examined: 1,
+                },
+                None => Explain {
+                    index: None,
+                    search: Search::Scan,
+                    rows_examined: 1,
+                },
+            })
+        }
+
         fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
             let mut indexes = self.indexes.lock().unwrap();
             indexes.push(fields.iter().map(|s| (*s).to_string()).collect());
             Ok(IndexHandle(indexes.len() - 1))
         }
+
+        fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+            self.add_index(&[&format!("{} operator", field)])
+        }
+
+        fn columns(&self) -> Vec<String> {
+            self.data.keys().cloned().collect()
+        }
+
+        fn index_bytes(&self) -> Vec<usize> {
+            let indexes = self.indexes.lock().unwrap();
+            indexes.iter().map(|fields| fields.len() * 100).collect()
+        }
+
+        fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+            Box::new(std::iter::once(self.data.clone()))
+        }
     }
 
     #[test]
@@ -272,6 +659,216 @@ mod tests {
         assert_eq!(vec!["erk".to_string()], *indexes[0]);
     }
 
+    #[test]
+    fn reload_adds_existing_indexes() {
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("dummy1".to_string(), Box::new(DummyEnrichmentTable::new()));
+        let mut registry = super::TableRegistry::default();
+        registry.load(tables);
+        assert_eq!(Ok(IndexHandle(0)), registry.add_index("dummy1", &["erk"]));
+        // An operator index on the same field is a different index.
+        assert_eq!(
+            Ok(IndexHandle(1)),
+            registry.add_operator_index("dummy1", "erk")
+        );
+        registry.finish_load();
+
+        // The config is reloaded, and the table rebuilt with new data.
+        registry.load(HashMap::new());
+        let indexes = Arc::new(Mutex::new(Vec::new()));
+        let mut reloaded = DummyEnrichmentTable::new_with_index(indexes.clone());
+        reloaded.data = btreemap! {
+            "field".to_string() => "reloaded".to_string()
+        };
+        assert_eq!(Ok(()), registry.reload("dummy1", Box::new(reloaded)));
+        assert_eq!(
+            vec![vec!["erk".to_string()], vec!["erk operator".to_string()]],
+            *indexes.lock().unwrap()
+        );
+        registry.finish_load();
+
+        assert_eq!(
+            Ok(btreemap! {
+                "field" => "reloaded"
+            }),
+            registry.as_readonly().find_table_row(
+                "dummy1",
+                &[Condition::Equals {
+                    field: "thing",
+                    value: "thang".to_string(),
+                }],
+                None,
+                Some(IndexHandle(0))
+            )
+        );
+    }
+
+    #[test]
+    fn reuses_existing_indexes() {
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        let indexes = Arc::new(Mutex::new(Vec::new()));
+        let dummy = DummyEnrichmentTable::new_with_index(indexes.clone());
+        tables.insert("dummy1".to_string(), Box::new(dummy));
+        let mut registry = super::TableRegistry::default();
+        registry.load(tables);
+        assert_eq!(Ok(IndexHandle(0)), registry.add_index("dummy1", &["erk"]));
+        registry.finish_load();
+
+        // The config is reloaded and the transforms add their indexes again.
+        registry.load(HashMap::new());
+        assert_eq!(Ok(IndexHandle(0)), registry.add_index("dummy1", &["erk"]));
+        assert_eq!(Ok(IndexHandle(1)), registry.add_index("dummy1", &["orc"]));
+        registry.finish_load();
+
+        assert_eq!(
+            vec![vec!["erk".to_string()], vec!["orc".to_string()]],
+            *indexes.lock().unwrap()
+        );
+        assert_eq!(2, registry.index_bytes()["dummy1"].len());
+    }
+
+    #[test]
+    fn removes_tables() {
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("dummy1".to_string(), Box::new(DummyEnrichmentTable::new()));
+        tables.insert("dummy2".to_string(), Box::new(DummyEnrichmentTable::new()));
+        let mut registry = super::TableRegistry::default();
+        registry.load(tables);
+        registry.add_index("dummy1", &["erk"]).unwrap();
+        registry.finish_load();
+
+        registry.load(HashMap::new());
+        registry.remove("dummy1");
+        assert_eq!(vec!["dummy2".to_string()], registry.table_ids());
+        registry.finish_load();
+
+        assert_eq!(
+            btreemap! {
+                "dummy2" => Vec::new(),
+            },
+            registry.index_bytes()
+        );
+    }
+
+    #[test]
+    fn upserts_only_into_writable_tables() {
+        use vrl_core::enrichment::TableSearch as _;
+
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("dummy1".to_string(), Box::new(DummyEnrichmentTable::new()));
+        let registry = super::TableRegistry::default();
+        registry.load(tables);
+        let tables = registry.as_readonly();
+
+        assert_eq!(
+            Err("finish_load not called".to_string()),
+            tables.upsert_table_row("dummy1", BTreeMap::new())
+        );
+
+        registry.finish_load();
+        assert_eq!(
+            Err("table can't be written to".to_string()),
+            tables.upsert_table_row("dummy1", BTreeMap::new())
+        );
+        assert_eq!(
+            Err("table 'dummy2' not loaded, available tables are: dummy1".to_string()),
+            tables.upsert_table_row("dummy2", BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn explains_searches_as_objects() {
+        use vrl_core::enrichment::TableSearch as _;
+
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("dummy1".to_string(), Box::new(DummyEnrichmentTable::new()));
+        let mut registry = super::TableRegistry::default();
+        registry.load(tables);
+        let handle = registry.add_index("dummy1", &["erk"]).unwrap();
+        registry.finish_load();
+        let tables = registry.as_readonly();
+
+        assert_eq!(
+            Ok(Some(btreemap! {
+                "index" => vec!["erk"],
+                "search" => "hash",
+                "rows_examined" => 1,
+            })),
+            tables.explain_table_search("dummy1", &[], Some(handle))
+        );
+        assert_eq!(
+            Ok(Some(btreemap! {
+                "index" => Value::Null,
+                "search" => "scan",
+                "rows_examined" => 1,
+            })),
+            tables.explain_table_search("dummy1", &[], None)
+        );
+    }
+
+    #[test]
+    fn can_not_reload_unknown_table() {
+        let registry = super::TableRegistry::default();
+        registry.load(HashMap::new());
+
+        assert_eq!(
+            Err("table 'dummy1' not loaded, available tables are: ".to_string()),
+            registry.reload("dummy1", Box::new(DummyEnrichmentTable::new()))
+        );
+    }
+
+    #[test]
+    fn reports_index_bytes() {
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("dummy1".to_string(), Box::new(DummyEnrichmentTable::new()));
+        tables.insert("dummy2".to_string(), Box::new(DummyEnrichmentTable::new()));
+        let mut registry = super::TableRegistry::default();
+        registry.load(tables);
+        registry.add_index("dummy1", &["erk"]).unwrap();
+        registry.add_index("dummy1", &["erk", "orc"]).unwrap();
+        registry.finish_load();
+
+        assert_eq!(
+            btreemap! {
+                "dummy1" => vec![
+                    (vec!["erk".to_string()], 100),
+                    (vec!["erk".to_string(), "orc".to_string()], 200),
+                ],
+                "dummy2" => Vec::new(),
+            },
+            registry.index_bytes()
+        );
+    }
+
+    #[test]
+    fn can_not_add_index_to_unknown_table() {
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("dummy2".to_string(), Box::new(DummyEnrichmentTable::new()));
+        tables.insert("dummy1".to_string(), Box::new(DummyEnrichmentTable::new()));
+        let mut registry = super::TableRegistry::default();
+        registry.load(tables);
+
+        // Fails whilst building the transforms that reference the table, before any lookups.
+        assert_eq!(
+            Err("table 'dumy1' not loaded, available tables are: dummy1, dummy2".to_string()),
+            registry.add_index("dumy1", &["erk"])
+        );
+
+        registry.finish_load();
+        assert_eq!(
+            Err("table 'dumy1' not loaded, available tables are: dummy1, dummy2".to_string()),
+            registry.as_readonly().find_table_row(
+                "dumy1",
+                &[Condition::Equals {
+                    field: "thing",
+                    value: "thang".to_string(),
+                }],
+                None,
+                None
+            )
+        );
+    }
+
     #[test]
     fn can_not_find_table_row_before_finish() {
         let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
@@ -289,6 +886,7 @@ mod tests {
                     field: "thing",
                     value: "thang".to_string(),
                 }],
+                None,
                 None
             )
         );
@@ -330,6 +928,7 @@ mod tests {
                     field: "thing",
                     value: "thang".to_string(),
                 }],
+                None,
                 None
             )
         );
@@ -360,4 +959,43 @@ mod tests {
 
         assert_eq!(vec!["dummy1".to_string(), "dummy2".to_string()], table_ids,);
     }
+
+    #[test]
+    fn snapshots_keep_tables_across_reloads() {
+        use vrl_core::enrichment::TableSearch as _;
+
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("dummy1".to_string(), Box::new(DummyEnrichmentTable::new()));
+        let registry = super::TableRegistry::default();
+        registry.load(tables);
+        registry.finish_load();
+
+        let search = registry.as_readonly();
+        let snapshot = registry.snapshot();
+
+        registry.load(HashMap::new());
+        registry
+            .reload(
+                "dummy1",
+                Box::new(DummyEnrichmentTable {
+                    data: btreemap! { "field" => "reloaded" },
+                    indexes: Arc::new(Mutex::new(Vec::new())),
+                }),
+            )
+            .unwrap();
+        registry.finish_load();
+
+        let condition = [Condition::Equals {
+            field: "field",
+            value: "value".to_string(),
+        }];
+        assert_eq!(
+            Ok(btreemap! { "field" => "reloaded" }),
+            search.find_table_row("dummy1", &condition, None, None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "field" => "result" }),
+            snapshot.find_table_row("dummy1", &condition, None, None)
+        );
+    }
 }
@@ -1,18 +1,184 @@
 use crate::Value;
 use dyn_clone::DynClone;
+use regex::Regex;
 use std::collections::BTreeMap;
+use std::net::IpAddr;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct IndexHandle(pub usize);
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Conditions on a field that isn't a column of the table match no rows. An empty cell is equal
+/// to an empty value.
+#[derive(Clone, Debug)]
 pub enum Condition<'a> {
-    Equals { field: &'a str, value: String },
+    Equals {
+        field: &'a str,
+        value: String,
+    },
+    /// Matches rows where the field holds a network, in CIDR notation, that contains the IP.
+    IpInCidr {
+        field: &'a str,
+        ip: IpAddr,
+    },
+    /// Matches rows where the field matches the wildcard pattern.
+    Glob {
+        field: &'a str,
+        pattern: Glob,
+    },
+    /// Matches rows where the field holds a set of values, split by the separator, that contains
+    /// the value. Whitespace around the members of the set and the value is ignored, and each
+    /// member is compared with the value as `Equals` compares the field with its value.
+    Contains {
+        field: &'a str,
+        value: String,
+        separator: char,
+    },
+    /// Matches rows where the field is an empty cell.
+    IsEmpty {
+        field: &'a str,
+    },
+    /// Matches rows where the field is a cell that isn't empty.
+    IsPresent {
+        field: &'a str,
+    },
+    /// Matches the rows where the field holds the number closest to the value, in the given
+    /// direction. Rows where the field doesn't hold a number never match.
+    ///
+    /// All the rows holding the closest number match, so a lookup only finds a single row if that
+    /// number is unique amongst the rows matching the other conditions.
+    Nearest {
+        field: &'a str,
+        value: f64,
+        direction: Direction,
+    },
+}
+
+/// Compares the values of `Condition::Nearest` by their bits, so a condition is always equal to
+/// itself, even when its value is NaN.
+impl<'a> PartialEq for Condition<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Condition::Equals { field, value }, Condition::Equals { field: f, value: v }) => {
+                field == f && value == v
+            }
+            (Condition::IpInCidr { field, ip }, Condition::IpInCidr { field: f, ip: i }) => {
+                field == f && ip == i
+            }
+            (
+                Condition::Glob { field, pattern },
+                Condition::Glob {
+                    field: f,
+                    pattern: p,
+                },
+            ) => field == f && pattern == p,
+            (
+                Condition::Contains {
+                    field,
+                    value,
+                    separator,
+                },
+                Condition::Contains {
+                    field: f,
+                    value: v,
+                    separator: s,
+                },
+            ) => field == f && value == v && separator == s,
+            (Condition::IsEmpty { field }, Condition::IsEmpty { field: f })
+            | (Condition::IsPresent { field }, Condition::IsPresent { field: f }) => field == f,
+            (
+                Condition::Nearest {
+                    field,
+                    value,
+                    direction,
+                },
+                Condition::Nearest {
+                    field: f,
+                    value: v,
+                    direction: d,
+                },
+            ) => field == f && value.to_bits() == v.to_bits() && direction == d,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Eq for Condition<'a> {}
+
+/// The direction to search in for a `Condition::Nearest`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    /// The closest number either side of the value. When numbers above and below the value are
+    /// equally close the lower one is used. Values outside the range of the numbers find the
+    /// smallest or largest number.
+    Nearest,
+    /// The largest number that is less than or equal to the value. Values below the smallest
+    /// number find no rows.
+    Floor,
+    /// The smallest number that is greater than or equal to the value. Values above the largest
+    /// number find no rows.
+    Ceiling,
+}
+
+/// A wildcard pattern where `*` matches any run of characters and `?` matches any single
+/// character. A `\` makes the character following it match literally, so `\*` matches a `*`.
+///
+/// Like equality conditions, matching is case insensitive. The pattern is compiled when the
+/// `Glob` is created so it isn't recompiled for every row it is matched against.
+#[derive(Clone, Debug)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        let mut source = String::from("(?is)^");
+        let mut chars = pattern.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => source.push_str(".*"),
+                '?' => source.push('.'),
+                // A trailing backslash has nothing to escape so matches itself.
+                '\\' => source.push_str(&regex::escape(&chars.next().unwrap_or('\\').to_string())),
+                c => source.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        source.push('$');
+
+        Self {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&source).expect("all literals in the glob are escaped"),
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+impl PartialEq for Glob {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
 }
 
+impl Eq for Glob {}
+
 pub trait TableSetup: DynClone {
     fn table_ids(&self) -> Vec<String>;
     fn add_index(&mut self, table: &str, fields: &[&str]) -> Result<IndexHandle, String>;
+
+    /// Adds an index for searching the field with an operator, such as `nearest` or `contains`.
+    /// Tables that can't index operators add an index for equality on the field.
+    fn add_operator_index(&mut self, table: &str, field: &str) -> Result<IndexHandle, String> {
+        self.add_index(table, &[field])
+    }
+
     fn as_readonly(&self) -> Box<dyn TableSearch + Send + Sync>;
 }
 
@@ -23,8 +189,27 @@ pub trait TableSearch: DynClone + std::fmt::Debug {
         &'a self,
         table: &str,
         condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
         index: Option<IndexHandle>,
     ) -> Result<BTreeMap<String, Value>, String>;
+
+    /// Writes the row to the table, replacing the row with the same key. Errors unless the table
+    /// can be written to whilst serving lookups.
+    fn upsert_table_row(&self, table: &str, _row: BTreeMap<String, Value>) -> Result<(), String> {
+        Err(format!("table '{}' can't be written to", table))
+    }
+
+    /// Explains how the table would search for the rows matching the condition, as an object
+    /// giving the fields of the `index` used, the kind of `search` and the number of
+    /// `rows_examined`. Returns `None` if the table can't explain its searches.
+    fn explain_table_search<'a>(
+        &'a self,
+        _table: &str,
+        _condition: &'a [Condition<'a>],
+        _index: Option<IndexHandle>,
+    ) -> Result<Option<BTreeMap<String, Value>>, String> {
+        Ok(None)
+    }
 }
 
 dyn_clone::clone_trait_object!(TableSearch);
@@ -52,8 +237,50 @@ impl TableSearch for EmptyEnrichmentTables {
         &self,
         _table: &str,
         _condition: &'a [Condition<'a>],
+        _select: Option<&[String]>,
         _index: Option<IndexHandle>,
     ) -> Result<BTreeMap<String, Value>, String> {
         Err("no data found".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches_wildcards() {
+        let cases = vec![
+            ("*.internal.example.com", "db.internal.example.com", true),
+            ("*.internal.example.com", "internal.example.com", false),
+            ("db-*.example.com", "db-01.example.com", true),
+            ("db-*.example.com", "web-01.example.com", false),
+            ("db.*", "db.example.com", true),
+            ("db.*", "dbexample.com", false),
+            ("db-??", "db-01", true),
+            ("db-??", "db-1", false),
+            ("DB-*", "db-01", true),
+            ("*", "", true),
+        ];
+
+        for (pattern, value, expected) in cases {
+            assert_eq!(
+                expected,
+                Glob::new(pattern).is_match(value),
+                "{} against {}",
+                pattern,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn glob_escapes_wildcards() {
+        let glob = Glob::new(r"what\?\*");
+
+        assert!(glob.is_match("what?*"));
+        assert!(!glob.is_match("whats*"));
+        assert!(!glob.is_match("what?s"));
+        assert!(Glob::new(r"trailing\").is_match(r"trailing\"));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::get_enrichment_table_record::GetEnrichmentTableRecordFn;
+use vrl::prelude::*;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ExplainEnrichmentTableLookup;
+impl Function for ExplainEnrichmentTableLookup {
+    fn identifier(&self) -> &'static str {
+        "explain_enrichment_table_lookup"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "table",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "condition",
+                kind: kind::OBJECT,
+                required: true,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[]
+    }
+
+    fn compile(&self, state: &state::Compiler, mut arguments: ArgumentList) -> Compiled {
+        let tables = state
+            .get_enrichment_tables()
+            .as_ref()
+            .map(|tables| {
+                tables
+                    .table_ids()
+                    .into_iter()
+                    .map(Value::from)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(Vec::new);
+
+        let table = arguments
+            .required_enum("table", &tables)?
+            .try_bytes_utf8_lossy()
+            .expect("table is not valid utf8")
+            .into_owned();
+        let condition = arguments.required_object("condition")?;
+
+        Ok(Box::new(ExplainEnrichmentTableLookupFn {
+            lookup: GetEnrichmentTableRecordFn::new(table, condition),
+        }))
+    }
+}
+
+/// Explains the lookup `get_enrichment_table_record` would make with the same condition, which
+/// uses the same index.
+#[derive(Debug, Clone)]
+pub struct ExplainEnrichmentTableLookupFn {
+    lookup: GetEnrichmentTableRecordFn,
+}
+
+impl Expression for ExplainEnrichmentTableLookupFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        self.lookup.explain(ctx)
+    }
+
+    fn update_state(
+        &mut self,
+        state: &mut state::Compiler,
+    ) -> std::result::Result<(), ExpressionError> {
+        self.lookup.update_state(state)
+    }
+
+    fn type_def(&self, _: &state::Compiler) -> TypeDef {
+        TypeDef::new()
+            .fallible()
+            .add_object::<(), Kind>(
+                map! { (): Kind::Array | Kind::Bytes | Kind::Integer | Kind::Null },
+            )
+            .add_null()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{btreemap, TimeZone};
+    use std::collections::BTreeMap;
+    use vrl::enrichment;
+
+    #[derive(Clone, Debug)]
+    struct DummyEnrichmentTable;
+
+    impl enrichment::TableSearch for DummyEnrichmentTable {
+        fn find_table_row<'a>(
+            &self,
+            _table: &str,
+            _condition: &'a [enrichment::Condition<'a>],
+            _select: Option<&[String]>,
+            _index: Option<enrichment::IndexHandle>,
+        ) -> std::result::Result<BTreeMap<String, Value>, String> {
+            Err("not found".to_string())
+        }
+
+        fn explain_table_search<'a>(
+            &'a self,
+            table: &str,
+            condition: &'a [enrichment::Condition<'a>],
+            _index: Option<enrichment::IndexHandle>,
+        ) -> std::result::Result<Option<BTreeMap<String, Value>>, String> {
+            assert_eq!(table, "table");
+            assert_eq!(
+                condition,
+                vec![enrichment::Condition::Equals {
+                    field: "field",
+                    value: "value".to_string(),
+                }]
+            );
+
+            Ok(Some(btreemap! {
+                "index" => Value::Null,
+                "search" => "scan",
+                "rows_examined" => 3,
+            }))
+        }
+    }
+
+    #[test]
+    fn explain_table_search() {
+        let func = ExplainEnrichmentTableLookupFn {
+            lookup: GetEnrichmentTableRecordFn::new(
+                "table".to_string(),
+                btreemap! {
+                    "field" => expression::Literal::from("value"),
+                },
+            ),
+        };
+
+        let tz = TimeZone::default();
+        let enrichment_tables =
+            Some(&DummyEnrichmentTable as &(dyn vrl::enrichment::TableSearch + Send + Sync));
+
+        let mut object: Value = BTreeMap::new().into();
+        let mut runtime_state = vrl::state::Runtime::default();
+        let mut ctx = Context::new(&mut object, &mut runtime_state, &tz, enrichment_tables);
+
+        assert_eq!(
+            Ok(value!({ "index": null, "search": "scan", "rows_examined": 3 })),
+            func.resolve(&mut ctx)
+        );
+    }
+}
@@ -20,6 +20,11 @@ impl Function for GetEnrichmentTableRecord {
                 kind: kind::OBJECT,
                 required: true,
             },
+            Parameter {
+                keyword: "select",
+                kind: kind::ARRAY,
+                required: false,
+            },
         ]
     }
 
@@ -46,10 +51,12 @@ impl Function for GetEnrichmentTableRecord {
             .expect("table is not valid utf8")
             .into_owned();
         let condition = arguments.required_object("condition")?;
+        let select = arguments.optional("select");
 
         Ok(Box::new(GetEnrichmentTableRecordFn {
             table,
             condition,
+            select,
             index: None,
         }))
     }
@@ -59,27 +66,63 @@ impl Function for GetEnrichmentTableRecord {
 pub struct GetEnrichmentTableRecordFn {
     table: String,
     condition: BTreeMap<String, expression::Expr>,
+    select: Option<Box<dyn Expression>>,
     index: Option<enrichment::IndexHandle>,
 }
 
+impl GetEnrichmentTableRecordFn {
+    pub(crate) fn new(table: String, condition: BTreeMap<String, expression::Expr>) -> Self {
+        Self {
+            table,
+            condition,
+            select: None,
+            index: None,
+        }
+    }
+
+    /// Explains how the lookup would search the table rather than finding the row, returning
+    /// null if the table can't explain its searches.
+    pub(crate) fn explain(&self, ctx: &mut Context) -> Resolved {
+        let condition = self.resolve_condition(ctx)?;
+
+        let tables = ctx
+            .get_enrichment_tables()
+            .ok_or("enrichment tables not loaded")?;
+
+        let explain = tables.explain_table_search(&self.table, &condition, self.index)?;
+        Ok(explain.map(Value::Object).unwrap_or(Value::Null))
+    }
+
+    fn resolve_condition(&self, ctx: &mut Context) -> Result<Vec<enrichment::Condition>> {
+        self.condition
+            .iter()
+            .map(|(key, value)| condition(key, value.resolve(ctx)?))
+            .collect()
+    }
+}
+
 impl Expression for GetEnrichmentTableRecordFn {
     fn resolve(&self, ctx: &mut Context) -> Resolved {
-        let condition = self
-            .condition
-            .iter()
-            .map(|(key, value)| {
-                Ok(enrichment::Condition::Equals {
-                    field: key,
-                    value: value.resolve(ctx)?.try_bytes_utf8_lossy()?.into_owned(),
-                })
+        let condition = self.resolve_condition(ctx)?;
+
+        let select = self
+            .select
+            .as_ref()
+            .map(|select| {
+                select
+                    .resolve(ctx)?
+                    .try_array()?
+                    .iter()
+                    .map(|field| Ok(field.try_bytes_utf8_lossy()?.into_owned()))
+                    .collect::<Result<Vec<_>>>()
             })
-            .collect::<Result<Vec<enrichment::Condition>>>()?;
+            .transpose()?;
 
         let tables = ctx
             .get_enrichment_tables()
             .ok_or("enrichment tables not loaded")?;
 
-        let data = tables.find_table_row(&self.table, &condition, self.index)?;
+        let data = tables.find_table_row(&self.table, &condition, select.as_deref(), self.index)?;
         Ok(Value::Object(data))
     }
 
@@ -87,14 +130,23 @@ impl Expression for GetEnrichmentTableRecordFn {
         &mut self,
         state: &mut state::Compiler,
     ) -> std::result::Result<(), ExpressionError> {
+        // Index the fields compared for equality. If there are none the table may still be able to
+        // index an operator, such as `nearest`, on a single field.
+        let (operators, equals): (Vec<_>, Vec<_>) = self
+            .condition
+            .iter()
+            .partition(|(_, value)| value.type_def(state).is_object());
+        let fields = equals
+            .iter()
+            .map(|(field, _)| field.as_str())
+            .collect::<Vec<_>>();
+
         match state.get_enrichment_tables_mut() {
             Some(ref mut table) => {
-                let fields = self
-                    .condition
-                    .iter()
-                    .map(|(field, _)| field.as_ref())
-                    .collect::<Vec<_>>();
-                let index = table.add_index(&self.table, &fields)?;
+                let index = match (fields.is_empty(), operators.first()) {
+                    (true, Some((field, _))) => table.add_operator_index(&self.table, field)?,
+                    _ => table.add_index(&self.table, &fields)?,
+                };
 
                 // Store the index to use while searching.
                 self.index = Some(index);
@@ -109,7 +161,111 @@ impl Expression for GetEnrichmentTableRecordFn {
     fn type_def(&self, _: &state::Compiler) -> TypeDef {
         TypeDef::new()
             .fallible()
-            .add_object::<(), Kind>(map! { (): Kind::Bytes })
+            .add_object::<(), Kind>(map! { (): inner_kind() })
+    }
+}
+
+/// The kinds of the values of a row, which are strings unless the table gives its columns types.
+fn inner_kind() -> Kind {
+    Kind::Bytes | Kind::Integer | Kind::Float | Kind::Boolean | Kind::Timestamp | Kind::Null
+}
+
+/// Creates the condition on the field from its value. An object gives an operator, such as
+/// `{ "glob": "web-*" }`, and any other value is compared for equality.
+fn condition(field: &str, value: Value) -> Result<enrichment::Condition<'_>> {
+    let mut operator = match value {
+        Value::Object(operator) => operator,
+        value => {
+            return Ok(enrichment::Condition::Equals {
+                field,
+                value: value.try_bytes_utf8_lossy()?.into_owned(),
+            })
+        }
+    };
+
+    let condition = if let Some(pattern) = operator.remove("glob") {
+        enrichment::Condition::Glob {
+            field,
+            pattern: enrichment::Glob::new(&pattern.try_bytes_utf8_lossy()?),
+        }
+    } else if let Some(value) = operator.remove("contains") {
+        let separator = match operator.remove("separator") {
+            Some(separator) => {
+                let separator = separator.try_bytes_utf8_lossy()?;
+                let mut chars = separator.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(separator), None) => separator,
+                    _ => {
+                        return Err(format!(
+                            "separator for field '{}' must be a single character",
+                            field
+                        )
+                        .into())
+                    }
+                }
+            }
+            None => ',',
+        };
+        enrichment::Condition::Contains {
+            field,
+            value: value.try_bytes_utf8_lossy()?.into_owned(),
+            separator,
+        }
+    } else if let Some(value) = operator.remove("nearest") {
+        let value = match value {
+            Value::Integer(value) => value as f64,
+            value => value.try_float()?,
+        };
+        let direction = match operator.remove("direction") {
+            Some(direction) => match direction.try_bytes_utf8_lossy()?.as_ref() {
+                "nearest" => enrichment::Direction::Nearest,
+                "floor" => enrichment::Direction::Floor,
+                "ceiling" => enrichment::Direction::Ceiling,
+                direction => {
+                    return Err(format!(
+                        "unknown direction '{}' for field '{}', expected nearest, floor or ceiling",
+                        direction, field
+                    )
+                    .into())
+                }
+            },
+            None => enrichment::Direction::Nearest,
+        };
+        enrichment::Condition::Nearest {
+            field,
+            value,
+            direction,
+        }
+    } else if let Some(ip) = operator.remove("ip_in_cidr") {
+        let ip = ip.try_bytes_utf8_lossy()?;
+        enrichment::Condition::IpInCidr {
+            field,
+            ip: ip
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{}' is not an ip address for field '{}'", ip, field))?,
+        }
+    } else if let Some(empty) = operator.remove("is_empty") {
+        if empty.try_boolean()? {
+            enrichment::Condition::IsEmpty { field }
+        } else {
+            enrichment::Condition::IsPresent { field }
+        }
+    } else {
+        return Err(format!(
+            "no operator given for field '{}', expected one of glob, contains, nearest, ip_in_cidr or is_empty",
+            field
+        )
+        .into());
+    };
+
+    match operator.keys().next() {
+        Some(key) => Err(format!(
+            "unexpected key '{}' in the condition for field '{}'",
+            key, field
+        )
+        .into()),
+        None => Ok(condition),
     }
 }
 
@@ -138,6 +294,17 @@ mod tests {
             Ok(enrichment::IndexHandle(999))
         }
 
+        fn add_operator_index(
+            &mut self,
+            table: &str,
+            field: &str,
+        ) -> std::result::Result<enrichment::IndexHandle, String> {
+            assert_eq!("table", table);
+            assert_eq!("other", field);
+
+            Ok(enrichment::IndexHandle(1000))
+        }
+
         fn as_readonly(&self) -> Box<dyn enrichment::TableSearch + Send + Sync> {
             Box::new(self.clone())
         }
@@ -148,6 +315,7 @@ mod tests {
             &self,
             table: &str,
             condition: &'a [enrichment::Condition<'a>],
+            select: Option<&[String]>,
             index: Option<enrichment::IndexHandle>,
         ) -> std::result::Result<BTreeMap<String, Value>, String> {
             assert_eq!(table, "table");
@@ -160,10 +328,15 @@ mod tests {
             );
             assert_eq!(index, Some(enrichment::IndexHandle(999)));
 
-            Ok(btreemap! {
+            let row: BTreeMap<String, Value> = btreemap! {
                 "field".to_string() => "value".to_string(),
                 "field2".to_string() => "value2".to_string(),
-            })
+            };
+
+            Ok(row
+                .into_iter()
+                .filter(|(field, _)| select.map_or(true, |select| select.contains(field)))
+                .collect())
         }
     }
 
@@ -174,6 +347,7 @@ mod tests {
             condition: btreemap! {
                 "field" =>  expression::Literal::from("value"),
             },
+            select: None,
             index: Some(enrichment::IndexHandle(999)),
         };
 
@@ -190,6 +364,32 @@ mod tests {
         assert_eq!(Ok(value! ({ "field": "value", "field2": "value2" })), got);
     }
 
+    #[test]
+    fn find_table_row_select() {
+        let func = GetEnrichmentTableRecordFn {
+            table: "table".to_string(),
+            condition: btreemap! {
+                "field" =>  expression::Literal::from("value"),
+            },
+            select: Some(Box::new(expression::Array::from(vec![
+                expression::Literal::from("field2").into(),
+            ]))),
+            index: Some(enrichment::IndexHandle(999)),
+        };
+
+        let tz = TimeZone::default();
+        let enrichment_tables =
+            Some(&DummyEnrichmentTable as &(dyn vrl::enrichment::TableSearch + Send + Sync));
+
+        let mut object: Value = BTreeMap::new().into();
+        let mut runtime_state = vrl::state::Runtime::default();
+        let mut ctx = Context::new(&mut object, &mut runtime_state, &tz, enrichment_tables);
+
+        let got = func.resolve(&mut ctx);
+
+        assert_eq!(Ok(value! ({ "field2": "value2" })), got);
+    }
+
     #[test]
     fn add_indexes() {
         let mut func = GetEnrichmentTableRecordFn {
@@ -197,6 +397,30 @@ mod tests {
             condition: btreemap! {
                 "field" =>  expression::Literal::from("value"),
             },
+            select: None,
+            index: None,
+        };
+
+        let mut compiler =
+            state::Compiler::new_with_enrichment_tables(Box::new(DummyEnrichmentTable));
+
+        assert_eq!(Ok(()), func.update_state(&mut compiler));
+        assert_eq!(Some(enrichment::IndexHandle(999)), func.index);
+    }
+
+    #[test]
+    fn add_indexes_ignores_operators() {
+        let mut func = GetEnrichmentTableRecordFn {
+            table: "table".to_string(),
+            condition: btreemap! {
+                "field" => expression::Literal::from("value"),
+                "other" => expression::Container::new(expression::Variant::Object(
+                    expression::Object::from(btreemap! {
+                        "glob".to_string() => expression::Expr::from(expression::Literal::from("v*")),
+                    }),
+                )),
+            },
+            select: None,
             index: None,
         };
 
@@ -206,4 +430,114 @@ mod tests {
         assert_eq!(Ok(()), func.update_state(&mut compiler));
         assert_eq!(Some(enrichment::IndexHandle(999)), func.index);
     }
+
+    #[test]
+    fn add_operator_index() {
+        let mut func = GetEnrichmentTableRecordFn {
+            table: "table".to_string(),
+            condition: btreemap! {
+                "other" => expression::Container::new(expression::Variant::Object(
+                    expression::Object::from(btreemap! {
+                        "nearest".to_string() => expression::Expr::from(expression::Literal::from(5)),
+                    }),
+                )),
+            },
+            select: None,
+            index: None,
+        };
+
+        let mut compiler =
+            state::Compiler::new_with_enrichment_tables(Box::new(DummyEnrichmentTable));
+
+        assert_eq!(Ok(()), func.update_state(&mut compiler));
+        assert_eq!(Some(enrichment::IndexHandle(1000)), func.index);
+    }
+
+    #[test]
+    fn creates_operator_conditions() {
+        let operator = |operator: Value| condition("field", operator);
+
+        assert_eq!(
+            Ok(enrichment::Condition::Equals {
+                field: "field",
+                value: "value".to_string(),
+            }),
+            condition("field", value!("value"))
+        );
+        assert_eq!(
+            Ok(enrichment::Condition::Glob {
+                field: "field",
+                pattern: enrichment::Glob::new("web-*"),
+            }),
+            operator(value!({ "glob": "web-*" }))
+        );
+        assert_eq!(
+            Ok(enrichment::Condition::Contains {
+                field: "field",
+                value: "admin".to_string(),
+                separator: ',',
+            }),
+            operator(value!({ "contains": "admin" }))
+        );
+        assert_eq!(
+            Ok(enrichment::Condition::Contains {
+                field: "field",
+                value: "admin".to_string(),
+                separator: '|',
+            }),
+            operator(value!({ "contains": "admin", "separator": "|" }))
+        );
+        assert_eq!(
+            Ok(enrichment::Condition::Nearest {
+                field: "field",
+                value: 12.0,
+                direction: enrichment::Direction::Floor,
+            }),
+            operator(value!({ "nearest": 12, "direction": "floor" }))
+        );
+        assert_eq!(
+            Ok(enrichment::Condition::IpInCidr {
+                field: "field",
+                ip: "10.1.2.3".parse().unwrap(),
+            }),
+            operator(value!({ "ip_in_cidr": "10.1.2.3" }))
+        );
+        assert_eq!(
+            Ok(enrichment::Condition::IsEmpty { field: "field" }),
+            operator(value!({ "is_empty": true }))
+        );
+        assert_eq!(
+            Ok(enrichment::Condition::IsPresent { field: "field" }),
+            operator(value!({ "is_empty": false }))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_operators() {
+        let error = |operator: Value| match condition("field", operator) {
+            Err(ExpressionError::Error { message, .. }) => message,
+            result => panic!("unexpected result {:?}", result),
+        };
+
+        assert_eq!(
+            "no operator given for field 'field', expected one of glob, contains, nearest, ip_in_cidr or is_empty",
+            error(value!({ "like": "web-*" }))
+        );
+        assert_eq!(
+            "unexpected key 'separator' in the condition for field 'field'",
+            error(value!({ "glob": "web-*", "separator": "|" }))
+        );
+        assert_eq!(
+            "separator for field 'field' must be a single character",
+            error(value!({ "contains": "admin", "separator": "||" }))
+        );
+        assert_eq!(
+            "unknown direction 'up' for field 'field', expected nearest, floor or ceiling",
+            error(value!({ "nearest": 1.5, "direction": "up" }))
+        );
+        assert_eq!(
+            "'nope' is not an ip address for field 'field'",
+            error(value!({ "ip_in_cidr": "nope" }))
+        );
+    }
 }
@@ -38,6 +38,8 @@ mod encode_percent;
 mod ends_with;
 #[cfg(feature = "exists")]
 mod exists;
+#[cfg(feature = "explain_enrichment_table_lookup")]
+mod explain_enrichment_table_lookup;
 #[cfg(feature = "flatten")]
 mod flatten;
 #[cfg(feature = "float")]
@@ -230,6 +232,8 @@ mod truncate;
 mod unnest;
 #[cfg(feature = "upcase")]
 mod upcase;
+#[cfg(feature = "upsert_enrichment_table_record")]
+mod upsert_enrichment_table_record;
 #[cfg(feature = "uuid_v4")]
 mod uuid_v4;
 
@@ -277,6 +281,8 @@ pub use encode_percent::EncodePercent;
 pub use ends_with::EndsWith;
 #[cfg(feature = "exists")]
 pub use exists::Exists;
+#[cfg(feature = "explain_enrichment_table_lookup")]
+pub use explain_enrichment_table_lookup::ExplainEnrichmentTableLookup;
 #[cfg(feature = "flatten")]
 pub use flatten::Flatten;
 #[cfg(feature = "float")]
@@ -459,6 +465,8 @@ pub use truncate::Truncate;
 pub use unnest::Unnest;
 #[cfg(feature = "upcase")]
 pub use upcase::Upcase;
+#[cfg(feature = "upsert_enrichment_table_record")]
+pub use upsert_enrichment_table_record::UpsertEnrichmentTableRecord;
 #[cfg(feature = "uuid_v4")]
 pub use uuid_v4::UuidV4;
 
@@ -502,6 +510,8 @@ pub fn all() -> Vec<Box<dyn vrl::Function>> {
         Box::new(EndsWith),
         #[cfg(feature = "exists")]
         Box::new(Exists),
+        #[cfg(feature = "explain_enrichment_table_lookup")]
+        Box::new(ExplainEnrichmentTableLookup),
         #[cfg(feature = "get_enrichment_table_record")]
         Box::new(GetEnrichmentTableRecord),
         #[cfg(feature = "flatten")]
@@ -696,6 +706,8 @@ pub fn all() -> Vec<Box<dyn vrl::Function>> {
         Box::new(Unnest),
         #[cfg(feature = "upcase")]
         Box::new(Upcase),
+        #[cfg(feature = "upsert_enrichment_table_record")]
+        Box::new(UpsertEnrichmentTableRecord),
         #[cfg(feature = "uuid_v4")]
         Box::new(UuidV4),
     ]
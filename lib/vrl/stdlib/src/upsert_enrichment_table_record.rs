@@ -0,0 +1,168 @@
+use vrl::prelude::*;
+
+#[derive(Clone, Copy, Debug)]
+pub struct UpsertEnrichmentTableRecord;
+impl Function for UpsertEnrichmentTableRecord {
+    fn identifier(&self) -> &'static str {
+        "upsert_enrichment_table_record"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "table",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "record",
+                kind: kind::OBJECT,
+                required: true,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[]
+    }
+
+    fn compile(&self, state: &state::Compiler, mut arguments: ArgumentList) -> Compiled {
+        let tables = state
+            .get_enrichment_tables()
+            .as_ref()
+            .map(|tables| {
+                tables
+                    .table_ids()
+                    .into_iter()
+                    .map(Value::from)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(Vec::new);
+
+        let table = arguments
+            .required_enum("table", &tables)?
+            .try_bytes_utf8_lossy()
+            .expect("table is not valid utf8")
+            .into_owned();
+        let record = arguments.required("record");
+
+        Ok(Box::new(UpsertEnrichmentTableRecordFn { table, record }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UpsertEnrichmentTableRecordFn {
+    table: String,
+    record: Box<dyn Expression>,
+}
+
+impl Expression for UpsertEnrichmentTableRecordFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let record = self.record.resolve(ctx)?.try_object()?;
+
+        let tables = ctx
+            .get_enrichment_tables()
+            .ok_or("enrichment tables not loaded")?;
+
+        tables.upsert_table_row(&self.table, record)?;
+        Ok(Value::Null)
+    }
+
+    fn type_def(&self, _: &state::Compiler) -> TypeDef {
+        TypeDef::new().fallible().null()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::{btreemap, TimeZone};
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+    use vrl::enrichment;
+
+    #[derive(Clone, Debug, Default)]
+    struct DummyEnrichmentTable(Arc<Mutex<Vec<BTreeMap<String, Value>>>>);
+
+    impl enrichment::TableSearch for DummyEnrichmentTable {
+        fn find_table_row<'a>(
+            &self,
+            _table: &str,
+            _condition: &'a [enrichment::Condition<'a>],
+            _select: Option<&[String]>,
+            _index: Option<enrichment::IndexHandle>,
+        ) -> std::result::Result<BTreeMap<String, Value>, String> {
+            Err("not found".to_string())
+        }
+
+        fn upsert_table_row(
+            &self,
+            table: &str,
+            row: BTreeMap<String, Value>,
+        ) -> std::result::Result<(), String> {
+            assert_eq!(table, "table");
+            self.0.lock().unwrap().push(row);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn upsert_table_row() {
+        let func = UpsertEnrichmentTableRecordFn {
+            table: "table".to_string(),
+            record: value!({ "ip": "10.0.0.1", "reason": "scan" }).into_expression(),
+        };
+
+        let tz = TimeZone::default();
+        let table = DummyEnrichmentTable::default();
+        let enrichment_tables = Some(&table as &(dyn vrl::enrichment::TableSearch + Send + Sync));
+
+        let mut object: Value = BTreeMap::new().into();
+        let mut runtime_state = vrl::state::Runtime::default();
+        let mut ctx = Context::new(&mut object, &mut runtime_state, &tz, enrichment_tables);
+
+        assert_eq!(Ok(Value::Null), func.resolve(&mut ctx));
+        assert_eq!(
+            vec![btreemap! { "ip" => "10.0.0.1", "reason" => "scan" }],
+            *table.0.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn read_only_table() {
+        #[derive(Clone, Debug)]
+        struct ReadOnlyTable;
+
+        impl enrichment::TableSearch for ReadOnlyTable {
+            fn find_table_row<'a>(
+                &self,
+                _table: &str,
+                _condition: &'a [enrichment::Condition<'a>],
+                _select: Option<&[String]>,
+                _index: Option<enrichment::IndexHandle>,
+            ) -> std::result::Result<BTreeMap<String, Value>, String> {
+                Err("not found".to_string())
+            }
+        }
+
+        let func = UpsertEnrichmentTableRecordFn {
+            table: "table".to_string(),
+            record: value!({ "ip": "10.0.0.1" }).into_expression(),
+        };
+
+        let tz = TimeZone::default();
+        let enrichment_tables =
+            Some(&ReadOnlyTable as &(dyn vrl::enrichment::TableSearch + Send + Sync));
+
+        let mut object: Value = BTreeMap::new().into();
+        let mut runtime_state = vrl::state::Runtime::default();
+        let mut ctx = Context::new(&mut object, &mut runtime_state, &tz, enrichment_tables);
+
+        match func.resolve(&mut ctx) {
+            Err(ExpressionError::Error { message, .. }) => {
+                assert_eq!("table 'table' can't be written to", message)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -400,12 +400,34 @@ impl EnrichmentTableOuter {
 #[async_trait]
 #[typetag::serde(tag = "type")]
 pub trait EnrichmentTableConfig: core::fmt::Debug + Send + Sync + dyn_clone::DynClone {
+    /// Builds the table, which is registered under `name`.
     async fn build(
         &self,
+        name: &str,
         globals: &GlobalOptions,
     ) -> crate::Result<Box<dyn enrichment::Table + Send + Sync>>;
+
+    /// Builds the table to replace the copy that is serving lookups after its config has
+    /// changed. The existing copy keeps serving until the config has reloaded, so tables that
+    /// can load in the background should have loaded before returning.
+    async fn rebuild(
+        &self,
+        name: &str,
+        globals: &GlobalOptions,
+    ) -> crate::Result<Box<dyn enrichment::Table + Send + Sync>> {
+        self.build(name, globals).await
+    }
+
+    /// Whether the table is rebuilt each time the config is reloaded, even if its own config
+    /// hasn't changed, so that edits to the data it reads, such as a file, are picked up by
+    /// reloading Vector with SIGHUP.
+    fn reloads_data(&self) -> bool {
+        false
+    }
 }
 
+dyn_clone::clone_trait_object!(EnrichmentTableConfig);
+
 pub type EnrichmentTableDescription = ComponentDescription<Box<dyn EnrichmentTableConfig>>;
 
 inventory::collect!(EnrichmentTableDescription);
@@ -0,0 +1,319 @@
+//! Loads an enrichment table in the background so that Vector can start processing events before
+//! a large table has finished loading and indexing.
+//!
+//! Until the load completes the table contains no data and lookups fail according to the
+//! configured `WhileLoading` policy. The columns are known up front, so indexes requested in the
+//! meantime are checked against them, recorded and built on the loaded table before it starts
+//! serving lookups.
+use super::util::{emit_index_bytes, inner_handle, validate_fields, Indexes};
+use crate::internal_events::{EnrichmentTableLoadFailed, EnrichmentTableLoaded};
+use arc_swap::ArcSwapOption;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use vector_core::enrichment::{Condition, Explain, IndexHandle, IndexKind, Table};
+use vrl::Value;
+
+/// How lookups behave whilst the table is still loading.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WhileLoading {
+    /// Lookups behave as if the table contains no matching rows.
+    NoMatch,
+    /// Lookups error, stating that the table is still loading.
+    Error,
+}
+
+impl Default for WhileLoading {
+    fn default() -> Self {
+        Self::NoMatch
+    }
+}
+
+struct Loaded {
+    table: Box<dyn Table + Send + Sync>,
+    /// Maps the handles we have returned from `add_index` to the handles of the loaded table.
+    handles: Vec<IndexHandle>,
+}
+
+#[derive(Clone)]
+pub struct Background {
+    name: String,
+    while_loading: WhileLoading,
+    /// The columns the loaded table will have.
+    columns: Vec<String>,
+    indexes: Indexes,
+    loaded: Arc<ArcSwapOption<Loaded>>,
+}
+
+impl Background {
+    /// Spawns a task running the given load future. The returned table serves lookups once the
+    /// future has completed, and has the given `columns` until then. The `name` identifies the
+    /// table in internal events.
+    pub fn spawn<F>(
+        name: impl Into<String>,
+        while_loading: WhileLoading,
+        columns: Vec<String>,
+        load: F,
+    ) -> Self
+    where
+        F: Future<Output = crate::Result<Box<dyn Table + Send + Sync>>> + Send + 'static,
+    {
+        let table = Self {
+            name: name.into(),
+            while_loading,
+            columns,
+            indexes: Indexes::default(),
+            loaded: Arc::new(ArcSwapOption::empty()),
+        };
+
+        let background = table.clone();
+        tokio::spawn(async move {
+            let start = Instant::now();
+            match load
+                .await
+                .and_then(|table| background.finish(table).map_err(Into::into))
+            {
+                Ok(()) => emit!(EnrichmentTableLoaded {
+                    name: &background.name,
+                    elapsed: start.elapsed(),
+                }),
+                Err(error) => emit!(EnrichmentTableLoadFailed {
+                    name: &background.name,
+                    error,
+                }),
+            }
+        });
+
+        table
+    }
+
+    /// Returns true once the table has been loaded and is serving lookups.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded.load().is_some()
+    }
+
+    /// Builds the indexes requested so far on the loaded table and swaps it in.
+    fn finish(&self, mut table: Box<dyn Table + Send + Sync>) -> Result<(), String> {
+        let (indexes, handles) = self.indexes.build(&mut *table)?;
+        self.loaded.store(Some(Arc::new(Loaded { table, handles })));
+        emit_index_bytes(&self.name, indexes.iter().map(|(fields, _)| fields), self);
+
+        Ok(())
+    }
+
+    /// The error for lookups whilst the table is still loading.
+    fn loading_error(&self) -> String {
+        match self.while_loading {
+            WhileLoading::NoMatch => "no rows found".to_string(),
+            WhileLoading::Error => "table is still loading".to_string(),
+        }
+    }
+
+    /// Records an index of the given kind to build on the loaded table, adding it straight away
+    /// if the table has already loaded.
+    fn add_index_of_kind(&self, fields: &[&str], kind: IndexKind) -> Result<IndexHandle, String> {
+        validate_fields(&self.columns, fields)?;
+
+        let loaded = &self.loaded;
+        self.indexes.add(fields, kind, |position| {
+            if let Some(loaded) = &*loaded.load() {
+                // The table has already loaded, which happens when the config is reloaded. The
+                // loaded table is shared with running transforms so we add the index to a copy.
+                let mut table = dyn_clone::clone_box(&*loaded.table);
+                let mut handles = loaded.handles.clone();
+                handles.push(kind.add_to(&mut *table, fields)?);
+                loaded.store(Some(Arc::new(Loaded { table, handles })));
+            }
+
+            Ok(IndexHandle(position))
+        })
+    }
+}
+
+impl Table for Background {
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        match &*self.loaded.load() {
+            Some(loaded) => {
+                let index = inner_handle(&loaded.handles, index);
+                loaded.table.find_table_row(condition, select, index)
+            }
+            None => Err(self.loading_error()),
+        }
+    }
+
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        match &*self.loaded.load() {
+            Some(loaded) => {
+                let index = inner_handle(&loaded.handles, index);
+                loaded.table.explain(condition, index)
+            }
+            None => None,
+        }
+    }
+
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(fields, IndexKind::Equals)
+    }
+
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(&[field], IndexKind::Operator)
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.columns.clone()
+    }
+
+    /// Returns no indexes until the table has loaded.
+    fn index_bytes(&self) -> Vec<usize> {
+        match &*self.loaded.load() {
+            Some(loaded) => {
+                let bytes = loaded.table.index_bytes();
+                loaded
+                    .handles
+                    .iter()
+                    .map(|IndexHandle(handle)| bytes.get(*handle).copied().unwrap_or_default())
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        match self.loaded.load_full() {
+            // The loaded table can be swapped out at any time, so the rows can't borrow from it.
+            Some(loaded) => Box::new(loaded.table.iter_rows().collect::<Vec<_>>().into_iter()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+impl std::fmt::Debug for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &*self.loaded.load() {
+            Some(_) => write!(f, "Background {} loaded", self.name),
+            None => write!(f, "Background {} loading", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrichment_tables::file::File;
+    use shared::btreemap;
+    use tokio::sync::oneshot;
+    use tokio::time::{sleep, timeout, Duration};
+
+    fn file() -> crate::Result<Box<dyn Table + Send + Sync>> {
+        Ok(Box::new(File::new(
+            vec![
+                vec!["zip".to_string(), "zup".to_string()],
+                vec!["zirp".to_string(), "zurp".to_string()],
+            ],
+            vec!["field1".to_string(), "field2".to_string()],
+        )))
+    }
+
+    fn columns() -> Vec<String> {
+        vec!["field1".to_string(), "field2".to_string()]
+    }
+
+    async fn wait_for_load(table: &Background) {
+        timeout(Duration::from_secs(5), async {
+            while !table.is_loaded() {
+                sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("table did not load");
+    }
+
+    #[tokio::test]
+    async fn serves_after_load() {
+        let (tx, rx) = oneshot::channel::<()>();
+        let mut table = Background::spawn("test", WhileLoading::Error, columns(), async move {
+            rx.await.unwrap();
+            file()
+        });
+
+        let handle = table.add_index(&["field1"]).unwrap();
+        let condition = Condition::Equals {
+            field: "field1",
+            value: "zirp".to_string(),
+        };
+
+        assert_eq!(
+            Err("table is still loading".to_string()),
+            table.find_table_row(&[condition.clone()], None, Some(handle))
+        );
+
+        tx.send(()).unwrap();
+        wait_for_load(&table).await;
+
+        assert_eq!(
+            Ok(btreemap! {
+                "field1" => "zirp",
+                "field2" => "zurp",
+            }),
+            table.find_table_row(&[condition], None, Some(handle))
+        );
+    }
+
+    #[tokio::test]
+    async fn no_match_while_loading() {
+        let (_tx, rx) = oneshot::channel::<()>();
+        let mut table = Background::spawn("test", WhileLoading::NoMatch, columns(), async move {
+            let _ = rx.await;
+            file()
+        });
+
+        let condition = Condition::Equals {
+            field: "field1",
+            value: "zirp".to_string(),
+        };
+
+        assert_eq!(
+            Err("no rows found".to_string()),
+            table.find_table_row(&[condition], None, None)
+        );
+        assert_eq!(columns(), table.columns());
+        assert_eq!(
+            Err(
+                "field(s) 'field3' not in table, available columns are: field1, field2".to_string()
+            ),
+            table.add_index(&["field3"])
+        );
+    }
+
+    #[tokio::test]
+    async fn adds_index_after_load() {
+        let mut table = Background::spawn("test", WhileLoading::Error, columns(), async { file() });
+        wait_for_load(&table).await;
+
+        let handle = table.add_index(&["field2"]).unwrap();
+        let condition = Condition::Equals {
+            field: "field2",
+            value: "zup".to_string(),
+        };
+
+        assert_eq!(
+            Ok(btreemap! {
+                "field1" => "zip",
+                "field2" => "zup",
+            }),
+            table.find_table_row(&[condition], None, Some(handle))
+        );
+    }
+}
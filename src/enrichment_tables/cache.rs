@@ -0,0 +1,400 @@
+//! A cache of lookup results that can be placed in front of any `Table`, so lookups repeating
+//! the same condition don't have to search the table each time.
+use super::normalize::Normalize;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use vector_core::enrichment::{Condition, Direction, Explain, IndexHandle, Table};
+use vrl::Value;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CacheConfig {
+    /// The maximum number of lookup results to hold, which must be greater than zero.
+    #[serde(default = "default_size")]
+    pub size: usize,
+    /// How long a cached result is served for, before the table is searched again.
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            size: default_size(),
+            ttl_secs: default_ttl_secs(),
+        }
+    }
+}
+
+const fn default_size() -> usize {
+    10_000
+}
+
+const fn default_ttl_secs() -> u64 {
+    60
+}
+
+/// The most shards the cache is split into, so that concurrent lookups rarely wait on each other.
+const MAX_SHARDS: usize = 16;
+
+impl CacheConfig {
+    /// Places a cache in front of the table, whose equality conditions are compared after
+    /// applying `normalize`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the size is zero.
+    pub fn wrap(
+        &self,
+        table: Box<dyn Table + Send + Sync>,
+        normalize: &Normalize,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        if self.size == 0 {
+            return Err("cache size must be greater than zero".into());
+        }
+
+        Ok(Box::new(
+            Cached::new(table, self.size, Duration::from_secs(self.ttl_secs))
+                .with_normalize(normalize.clone()),
+        ))
+    }
+}
+
+/// A condition normalized so that the same search given in a different order, or with values
+/// that the table compares as equal, shares an entry.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum CacheCondition {
+    Equals(String, String),
+    IpInCidr(String, IpAddr),
+    Glob(String, String),
+    Contains(String, String, char),
+    IsEmpty(String),
+    IsPresent(String),
+    /// The value is held as its bits, since floats can't be hashed.
+    Nearest(String, u64, Direction),
+}
+
+/// The normalized conditions, and the columns selected since they change the result.
+type CacheKey = (Vec<CacheCondition>, Option<Vec<String>>);
+
+type CacheEntry = (Instant, BTreeMap<String, Value>);
+
+/// Caches the results of lookups that found a row. Errors, such as finding no rows or the table
+/// still loading, are never cached so the table is searched again by the next lookup.
+///
+/// The entries are split between shards by the hash of their key, each with its own lock.
+///
+/// Cloning creates an empty cache. Tables are cloned when the config is reloaded, so a reload
+/// always clears the cache.
+pub struct Cached {
+    inner: Box<dyn Table + Send + Sync>,
+    size: usize,
+    ttl: Duration,
+    normalize: Normalize,
+    shards: Vec<Mutex<LruCache<CacheKey, CacheEntry>>>,
+}
+
+impl Cached {
+    /// # Panics
+    ///
+    /// Panics if the size is zero.
+    pub fn new(inner: Box<dyn Table + Send + Sync>, size: usize, ttl: Duration) -> Self {
+        assert!(size > 0, "cache size must be greater than zero");
+
+        let shards = size.min(MAX_SHARDS);
+        // Round up so the shards hold at least `size` entries between them.
+        let shard_size = (size + shards - 1) / shards;
+
+        Self {
+            inner,
+            size,
+            ttl,
+            normalize: Normalize::default(),
+            shards: (0..shards)
+                .map(|_| Mutex::new(LruCache::new(shard_size)))
+                .collect(),
+        }
+    }
+
+    /// Sets how the values of equality conditions are normalized, which must match the table so
+    /// that lookups it finds the same rows for share an entry.
+    pub fn with_normalize(mut self, normalize: Normalize) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    fn shard(&self, key: &CacheKey) -> &Mutex<LruCache<CacheKey, CacheEntry>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % self.shards.len()]
+    }
+
+    fn key(&self, condition: &[Condition], select: Option<&[String]>) -> CacheKey {
+        let mut conditions = condition
+            .iter()
+            .map(|condition| match condition {
+                Condition::Equals { field, value } => {
+                    CacheCondition::Equals((*field).to_string(), self.normalize.key(value))
+                }
+                Condition::IpInCidr { field, ip } => {
+                    CacheCondition::IpInCidr((*field).to_string(), *ip)
+                }
+                Condition::Glob { field, pattern } => {
+                    CacheCondition::Glob((*field).to_string(), pattern.pattern().to_string())
+                }
+                Condition::Contains {
+                    field,
+                    value,
+                    separator,
+                } => CacheCondition::Contains(
+                    (*field).to_string(),
+                    // Members are compared trimmed and ignoring case.
+                    value.trim().to_lowercase(),
+                    *separator,
+                ),
+                Condition::IsEmpty { field } => CacheCondition::IsEmpty((*field).to_string()),
+                Condition::IsPresent { field } => CacheCondition::IsPresent((*field).to_string()),
+                Condition::Nearest {
+                    field,
+                    value,
+                    direction,
+                } => CacheCondition::Nearest((*field).to_string(), value.to_bits(), *direction),
+            })
+            .collect::<Vec<_>>();
+        conditions.sort();
+
+        (conditions, select.map(<[String]>::to_vec))
+    }
+}
+
+impl Clone for Cached {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone(), self.size, self.ttl).with_normalize(self.normalize.clone())
+    }
+}
+
+impl Table for Cached {
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        let key = self.key(condition, select);
+        let shard = self.shard(&key);
+
+        if let Some((inserted, row)) = shard.lock().unwrap().get(&key) {
+            if inserted.elapsed() < self.ttl {
+                return Ok(row.clone());
+            }
+        }
+
+        // The lock isn't held whilst searching so a slow search doesn't block other lookups.
+        let row = self.inner.find_table_row(condition, select, index)?;
+        shard
+            .lock()
+            .unwrap()
+            .put(key, (Instant::now(), row.clone()));
+
+        Ok(row)
+    }
+
+    /// Explains the search of the inner table, as if the lookup wasn't cached.
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        self.inner.explain(condition, index)
+    }
+
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        self.inner.add_index(fields)
+    }
+
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        self.inner.add_operator_index(field)
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.inner.columns()
+    }
+
+    fn index_bytes(&self) -> Vec<usize> {
+        self.inner.index_bytes()
+    }
+
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        self.inner.iter_rows()
+    }
+}
+
+impl std::fmt::Debug for Cached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cached {} entries", self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::btreemap;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Counts its lookups, finding no rows for lookups of "missing".
+    #[derive(Clone)]
+    struct CountingTable(Arc<AtomicUsize>);
+
+    impl Table for CountingTable {
+        fn find_table_row<'a>(
+            &self,
+            condition: &'a [Condition<'a>],
+            _select: Option<&[String]>,
+            _index: Option<IndexHandle>,
+        ) -> Result<BTreeMap<String, Value>, String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            match condition {
+                [Condition::Equals { value, .. }] if value == "missing" => {
+                    Err("no rows found".to_string())
+                }
+                _ => Ok(btreemap! {
+                    "field".to_string() => "result".to_string()
+                }),
+            }
+        }
+
+        fn add_index(&mut self, _fields: &[&str]) -> Result<IndexHandle, String> {
+            Ok(IndexHandle(0))
+        }
+
+        fn columns(&self) -> Vec<String> {
+            vec!["field".to_string()]
+        }
+
+        fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+            Box::new(std::iter::empty())
+        }
+    }
+
+    fn cached(ttl: Duration) -> (Cached, Arc<AtomicUsize>) {
+        let count = Arc::new(AtomicUsize::new(0));
+        let table = Cached::new(Box::new(CountingTable(Arc::clone(&count))), 10, ttl);
+        (table, count)
+    }
+
+    fn condition() -> Vec<Condition<'static>> {
+        vec![
+            Condition::Equals {
+                field: "one",
+                value: "1".to_string(),
+            },
+            Condition::Equals {
+                field: "two",
+                value: "2".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn serves_repeated_lookup_from_cache() {
+        let (table, count) = cached(Duration::from_secs(60));
+
+        let first = table.find_table_row(&condition(), None, None);
+        let mut reversed = condition();
+        reversed.reverse();
+        let second = table.find_table_row(&reversed, None, None);
+
+        assert_eq!(first, second);
+        assert_eq!(1, count.load(Ordering::SeqCst));
+
+        let other = vec![Condition::Equals {
+            field: "one",
+            value: "other".to_string(),
+        }];
+        table.find_table_row(&other, None, None).unwrap();
+        assert_eq!(2, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn shares_entries_of_normalized_values() {
+        let (table, count) = cached(Duration::from_secs(60));
+        let lookup = |value: &str| {
+            let condition = Condition::Equals {
+                field: "one",
+                value: value.to_string(),
+            };
+            table.find_table_row(&[condition], None, None).unwrap();
+        };
+
+        lookup("Value");
+        lookup("VALUE");
+
+        assert_eq!(1, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn doesnt_cache_errors() {
+        let (table, count) = cached(Duration::from_secs(60));
+        let condition = vec![Condition::Equals {
+            field: "one",
+            value: "missing".to_string(),
+        }];
+
+        assert!(table.find_table_row(&condition, None, None).is_err());
+        assert!(table.find_table_row(&condition, None, None).is_err());
+
+        assert_eq!(2, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn rejects_zero_size() {
+        let config = CacheConfig {
+            size: 0,
+            ttl_secs: 60,
+        };
+        let table = Box::new(CountingTable(Arc::new(AtomicUsize::new(0))));
+
+        assert_eq!(
+            "cache size must be greater than zero",
+            config
+                .wrap(table, &Normalize::default())
+                .map(|_| ())
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn expires_entries() {
+        let (table, count) = cached(Duration::from_secs(0));
+
+        table.find_table_row(&condition(), None, None).unwrap();
+        table.find_table_row(&condition(), None, None).unwrap();
+
+        assert_eq!(2, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn reload_clears_cache() {
+        let (table, count) = cached(Duration::from_secs(60));
+        table.find_table_row(&condition(), None, None).unwrap();
+
+        // The registry clones tables when the config is reloaded.
+        let reloaded = table.clone();
+        reloaded.find_table_row(&condition(), None, None).unwrap();
+
+        assert_eq!(2, count.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,652 @@
+use super::{
+    cache::CacheConfig,
+    file::FileC,
+    normalize::Normalize,
+    schema::{Schema, SchemaConfig},
+    util::{mask_v4, mask_v6, Network},
+};
+use crate::config::{EnrichmentTableConfig, EnrichmentTableDescription};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::mem::size_of;
+use std::net::IpAddr;
+use vector_core::enrichment::{Condition, Explain, IndexHandle, Search, Table};
+use vrl::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CidrConfig {
+    file: FileC,
+    /// The column holding the networks, in CIDR notation.
+    #[serde(default = "default_cidr_field")]
+    cidr_field: String,
+    cache: Option<CacheConfig>,
+    #[serde(default)]
+    schema: SchemaConfig,
+}
+
+impl Default for CidrConfig {
+    fn default() -> Self {
+        Self {
+            file: FileC::default(),
+            cidr_field: default_cidr_field(),
+            cache: None,
+            schema: SchemaConfig::default(),
+        }
+    }
+}
+
+fn default_cidr_field() -> String {
+    "cidr".to_string()
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "cidr")]
+impl EnrichmentTableConfig for CidrConfig {
+    async fn build(
+        &self,
+        _name: &str,
+        _globals: &crate::config::GlobalOptions,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        let (data, headers) = self.file.read()?;
+        let schema = self.schema.build(&headers, &data)?;
+        let table = Box::new(Cidr::new(data, headers, &self.cidr_field)?.with_schema(schema));
+
+        Ok(match &self.cache {
+            Some(cache) => cache.wrap(table, &Normalize::default())?,
+            None => table,
+        })
+    }
+
+    fn reloads_data(&self) -> bool {
+        true
+    }
+}
+
+inventory::submit! {
+    EnrichmentTableDescription::new::<CidrConfig>("cidr")
+}
+
+impl_generate_config_from_default!(CidrConfig);
+
+/// Maps the networks of each prefix length to the rows holding them. Sorted by prefix length,
+/// longest first, so the first network found containing an address is the most specific one.
+type Networks<T> = Vec<(u8, HashMap<T, Vec<usize>>)>;
+
+/// Returns the approximate memory used by the networks of each prefix in bytes.
+fn networks_bytes<T>(networks: &Networks<T>) -> usize {
+    networks.capacity() * size_of::<(u8, HashMap<T, Vec<usize>>)>()
+        + networks
+            .iter()
+            .map(|(_, addresses)| {
+                addresses.capacity() * (size_of::<T>() + size_of::<Vec<usize>>())
+                    + addresses
+                        .values()
+                        .map(|rows| rows.capacity() * size_of::<usize>())
+                        .sum::<usize>()
+            })
+            .sum::<usize>()
+}
+
+/// What a lookup searches the network column for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Target {
+    /// The networks containing the address.
+    Ip(IpAddr),
+    /// Exactly the network.
+    Network(Network),
+}
+
+impl Target {
+    fn matches(self, cell: &str) -> bool {
+        match (self, Network::parse(cell)) {
+            (Self::Ip(ip), Some(network)) => network.contains(ip),
+            (Self::Network(target), Some(network)) => target == network,
+            (_, None) => false,
+        }
+    }
+}
+
+/// A table of networks that is searched by IP address. Lookups return the row with the most
+/// specific network containing the address.
+///
+/// The IP address is given with a `Condition::IpInCidr` on the network column, or with a
+/// `Condition::Equals` on the network column whose value is an IP address. A `Condition::Equals`
+/// whose value is a network in CIDR notation finds the rows holding exactly that network. Other
+/// conditions on the network column are rejected, and conditions on other columns further
+/// restrict the rows that can match.
+#[derive(Clone)]
+pub struct Cidr {
+    data: Vec<Vec<String>>,
+    headers: Vec<String>,
+    schema: Schema,
+    cidr_field: String,
+    /// The position of the network column.
+    column: usize,
+    v4: Networks<u32>,
+    v6: Networks<u128>,
+}
+
+impl Cidr {
+    /// # Errors
+    ///
+    /// Errors if the network column is not in the headers or holds an invalid network.
+    pub fn new(
+        data: Vec<Vec<String>>,
+        headers: Vec<String>,
+        cidr_field: &str,
+    ) -> Result<Self, String> {
+        let column = headers
+            .iter()
+            .position(|header| header == cidr_field)
+            .ok_or_else(|| format!("field '{}' not in table", cidr_field))?;
+
+        let mut v4 = BTreeMap::<u8, HashMap<u32, Vec<usize>>>::new();
+        let mut v6 = BTreeMap::<u8, HashMap<u128, Vec<usize>>>::new();
+
+        for (idx, row) in data.iter().enumerate() {
+            match Network::parse(&row[column]) {
+                Some(Network::V4 { address, prefix }) => v4
+                    .entry(prefix)
+                    .or_default()
+                    .entry(address)
+                    .or_default()
+                    .push(idx),
+                Some(Network::V6 { address, prefix }) => v6
+                    .entry(prefix)
+                    .or_default()
+                    .entry(address)
+                    .or_default()
+                    .push(idx),
+                None => {
+                    return Err(format!(
+                        "invalid network '{}' in row {}",
+                        row[column],
+                        idx + 1
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            data,
+            headers,
+            schema: Schema::default(),
+            cidr_field: cidr_field.to_string(),
+            column,
+            v4: v4.into_iter().rev().collect(),
+            v6: v6.into_iter().rev().collect(),
+        })
+    }
+
+    /// Sets the types of the columns, which are all strings by default.
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Finds what a condition on the network column searches for, or `None` for conditions on
+    /// other columns.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the condition can't search networks, or its value is neither an IP address nor
+    /// a network.
+    fn target(&self, condition: &Condition) -> Result<Option<Target>, String> {
+        match condition {
+            Condition::IpInCidr { field, ip } if *field == self.cidr_field => {
+                Ok(Some(Target::Ip(*ip)))
+            }
+            Condition::Equals { field, value } if *field == self.cidr_field => {
+                match value.trim().parse::<IpAddr>() {
+                    Ok(ip) => Ok(Some(Target::Ip(ip))),
+                    Err(_) => Network::parse(value)
+                        .map(|network| Some(Target::Network(network)))
+                        .ok_or_else(|| {
+                            format!(
+                                "'{}' is not an ip address or network for field '{}'",
+                                value, self.cidr_field
+                            )
+                        }),
+                }
+            }
+            Condition::Glob { field, .. }
+            | Condition::Contains { field, .. }
+            | Condition::IsEmpty { field }
+            | Condition::IsPresent { field }
+            | Condition::Nearest { field, .. }
+                if *field == self.cidr_field =>
+            {
+                Err(format!(
+                    "only ip address and equality conditions are supported on field '{}'",
+                    self.cidr_field
+                ))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Checks the row against all the conditions.
+    fn row_matches(&self, condition: &[Condition], row: &[String]) -> bool {
+        let normalize = Normalize::default();
+        condition
+            .iter()
+            .all(|condition| match self.target(condition) {
+                Ok(Some(target)) => target.matches(&row[self.column]),
+                Ok(None) => super::util::condition_matches(
+                    &self.headers,
+                    &self.schema,
+                    &normalize,
+                    condition,
+                    row,
+                ),
+                Err(_) => false,
+            })
+    }
+
+    fn add_columns(&self, row: &[String], select: Option<&[String]>) -> BTreeMap<String, Value> {
+        super::util::row_columns(&self.headers, &self.schema, row, select)
+    }
+
+    /// Returns the rows holding exactly the network, that also match the rest of the condition.
+    /// Adds the number of rows checked against the condition to `examined`.
+    fn search_network<T>(
+        &self,
+        networks: &Networks<T>,
+        condition: &[Condition],
+        prefix: u8,
+        address: T,
+        examined: &mut usize,
+    ) -> Vec<&[String]>
+    where
+        T: std::hash::Hash + Eq,
+    {
+        networks
+            .iter()
+            .filter(|(network_prefix, _)| *network_prefix == prefix)
+            .filter_map(|(_, networks)| networks.get(&address))
+            .flatten()
+            .map(|idx| self.data[*idx].as_slice())
+            .inspect(|_| *examined += 1)
+            .filter(|row| self.row_matches(condition, row))
+            .collect()
+    }
+
+    /// Returns the rows of the most specific network containing the address, that also match the
+    /// rest of the condition. Adds the number of rows checked against the condition to
+    /// `examined`.
+    fn search<T, M>(
+        &self,
+        networks: &Networks<T>,
+        condition: &[Condition],
+        mask: M,
+        examined: &mut usize,
+    ) -> Vec<&[String]>
+    where
+        T: std::hash::Hash + Eq,
+        M: Fn(u8) -> T,
+    {
+        networks
+            .iter()
+            .filter_map(|(prefix, networks)| networks.get(&mask(*prefix)))
+            .map(|rows| {
+                *examined += rows.len();
+                rows.iter()
+                    .map(|idx| self.data[*idx].as_slice())
+                    .filter(|row| self.row_matches(condition, row))
+                    .collect::<Vec<_>>()
+            })
+            .find(|rows| !rows.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Returns the rows matching the condition, adding the number of rows checked against it to
+    /// `examined`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the condition doesn't give an IP address or network to search for, or has a
+    /// condition cidr tables don't support.
+    fn find_rows(
+        &self,
+        condition: &[Condition],
+        examined: &mut usize,
+    ) -> Result<Vec<&[String]>, String> {
+        if condition
+            .iter()
+            .any(|condition| matches!(condition, Condition::Nearest { .. }))
+        {
+            return Err("nearest conditions are not supported by cidr tables".to_string());
+        }
+
+        let mut target = None;
+        for condition in condition {
+            if let Some(found) = self.target(condition)? {
+                target.get_or_insert(found);
+            }
+        }
+
+        Ok(match target {
+            Some(Target::Ip(IpAddr::V4(ip))) => {
+                let ip = u32::from(ip);
+                self.search(&self.v4, condition, |prefix| mask_v4(ip, prefix), examined)
+            }
+            Some(Target::Ip(IpAddr::V6(ip))) => {
+                let ip = u128::from(ip);
+                self.search(&self.v6, condition, |prefix| mask_v6(ip, prefix), examined)
+            }
+            Some(Target::Network(Network::V4 { address, prefix })) => {
+                self.search_network(&self.v4, condition, prefix, address, examined)
+            }
+            Some(Target::Network(Network::V6 { address, prefix })) => {
+                self.search_network(&self.v6, condition, prefix, address, examined)
+            }
+            None => {
+                return Err(format!(
+                    "no ip address given for field '{}'",
+                    self.cidr_field
+                ))
+            }
+        })
+    }
+}
+
+impl Table for Cidr {
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        _index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        if let Some(select) = select {
+            super::util::validate_fields(&self.headers, select)?;
+        }
+
+        let rows = self.find_rows(condition, &mut 0)?;
+        match rows.as_slice() {
+            [row] => Ok(self.add_columns(row, select)),
+            [] => Err("no rows found".to_string()),
+            _ => Err(format!("{} rows found", rows.len())),
+        }
+    }
+
+    /// Lookups always search the networks, so this returns `None` only for conditions that can't
+    /// be searched.
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        _index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        let mut rows_examined = 0;
+        self.find_rows(condition, &mut rows_examined).ok()?;
+
+        Some(Explain {
+            index: Some(vec![self.cidr_field.clone()]),
+            search: Search::Networks,
+            rows_examined,
+        })
+    }
+
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        super::util::validate_fields(&self.headers, fields)?;
+
+        // Lookups always go through the networks, there is nothing further to index.
+        Ok(IndexHandle(0))
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.headers.clone()
+    }
+
+    /// Returns the memory used by the networks, which are built up front and searched by every
+    /// index, so they are only counted once.
+    fn index_bytes(&self) -> Vec<usize> {
+        vec![networks_bytes(&self.v4) + networks_bytes(&self.v6)]
+    }
+
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        Box::new(self.data.iter().map(move |row| self.add_columns(row, None)))
+    }
+}
+
+impl std::fmt::Debug for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cidr {} row(s)", self.data.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::btreemap;
+
+    fn cidr() -> Cidr {
+        Cidr::new(
+            vec![
+                vec!["10.0.0.0/8".to_string(), "private".to_string()],
+                vec!["10.1.0.0/16".to_string(), "office".to_string()],
+                vec!["10.1.2.0/24".to_string(), "lab".to_string()],
+                vec!["10.1.2.3".to_string(), "printer".to_string()],
+                vec!["2001:db8::/32".to_string(), "documentation".to_string()],
+                vec!["2001:db8:1::/48".to_string(), "site".to_string()],
+            ],
+            vec!["cidr".to_string(), "name".to_string()],
+            "cidr",
+        )
+        .unwrap()
+    }
+
+    fn find(cidr: &Cidr, ip: &str) -> Result<BTreeMap<String, Value>, String> {
+        let condition = Condition::IpInCidr {
+            field: "cidr",
+            ip: ip.parse().unwrap(),
+        };
+        cidr.find_table_row(&[condition], None, None)
+    }
+
+    #[test]
+    fn finds_most_specific_network() {
+        let cidr = cidr();
+
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.0.0.0/8", "name" => "private" }),
+            find(&cidr, "10.200.0.1")
+        );
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.1.0.0/16", "name" => "office" }),
+            find(&cidr, "10.1.9.9")
+        );
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.1.2.0/24", "name" => "lab" }),
+            find(&cidr, "10.1.2.4")
+        );
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.1.2.3", "name" => "printer" }),
+            find(&cidr, "10.1.2.3")
+        );
+    }
+
+    #[test]
+    fn finds_ipv6_network() {
+        let cidr = cidr();
+
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "2001:db8::/32", "name" => "documentation" }),
+            find(&cidr, "2001:db8:2::1")
+        );
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "2001:db8:1::/48", "name" => "site" }),
+            find(&cidr, "2001:db8:1::1")
+        );
+    }
+
+    #[test]
+    fn finds_network_with_equals() {
+        let condition = Condition::Equals {
+            field: "cidr",
+            value: "10.1.9.9".to_string(),
+        };
+
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.1.0.0/16", "name" => "office" }),
+            cidr().find_table_row(&[condition], None, None)
+        );
+    }
+
+    #[test]
+    fn finds_exact_network_with_equals() {
+        let cidr = cidr();
+        let find = |value: &str| {
+            let condition = Condition::Equals {
+                field: "cidr",
+                value: value.to_string(),
+            };
+            cidr.find_table_row(&[condition], None, None)
+        };
+
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.1.0.0/16", "name" => "office" }),
+            find("10.1.0.0/16")
+        );
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "2001:db8:1::/48", "name" => "site" }),
+            find("2001:db8:1::/48")
+        );
+        assert_eq!(Err("no rows found".to_string()), find("10.1.0.0/12"));
+        assert_eq!(
+            Err("'office' is not an ip address or network for field 'cidr'".to_string()),
+            find("office")
+        );
+    }
+
+    #[test]
+    fn rejects_other_conditions_on_networks() {
+        let condition = vec![
+            Condition::IpInCidr {
+                field: "cidr",
+                ip: "10.1.2.3".parse().unwrap(),
+            },
+            Condition::IsEmpty { field: "cidr" },
+        ];
+
+        assert_eq!(
+            Err(
+                "only ip address and equality conditions are supported on field 'cidr'".to_string()
+            ),
+            cidr().find_table_row(&condition, None, None)
+        );
+    }
+
+    #[test]
+    fn all_network_conditions_must_match() {
+        let condition = vec![
+            Condition::IpInCidr {
+                field: "cidr",
+                ip: "10.1.2.3".parse().unwrap(),
+            },
+            Condition::Equals {
+                field: "cidr",
+                value: "10.200.0.1".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.0.0.0/8", "name" => "private" }),
+            cidr().find_table_row(&condition, None, None)
+        );
+    }
+
+    #[test]
+    fn other_conditions_restrict_networks() {
+        let condition = vec![
+            Condition::IpInCidr {
+                field: "cidr",
+                ip: "10.1.2.3".parse().unwrap(),
+            },
+            Condition::Equals {
+                field: "name",
+                value: "office".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            Ok(btreemap! { "cidr" => "10.1.0.0/16", "name" => "office" }),
+            cidr().find_table_row(&condition, None, None)
+        );
+    }
+
+    #[test]
+    fn explains_network_lookups() {
+        let condition = vec![
+            Condition::IpInCidr {
+                field: "cidr",
+                ip: "10.1.2.3".parse().unwrap(),
+            },
+            Condition::Equals {
+                field: "name",
+                value: "office".to_string(),
+            },
+        ];
+
+        // The printer and lab networks are checked before the office network matches.
+        assert_eq!(
+            Some(Explain {
+                index: Some(vec!["cidr".to_string()]),
+                search: Search::Networks,
+                rows_examined: 3,
+            }),
+            cidr().explain(&condition, None)
+        );
+        assert_eq!(None, cidr().explain(&condition[1..], None));
+    }
+
+    #[test]
+    fn doesnt_find_network() {
+        let cidr = cidr();
+
+        assert_eq!(Err("no rows found".to_string()), find(&cidr, "192.168.0.1"));
+        assert_eq!(Err("no rows found".to_string()), find(&cidr, "2001:db9::1"));
+    }
+
+    #[test]
+    fn add_index_validates_fields() {
+        let mut cidr = cidr();
+
+        assert_eq!(Ok(IndexHandle(0)), cidr.add_index(&["cidr", "name"]));
+        assert_eq!(
+            Err("field(s) 'nmae' not in table, available columns are: cidr, name".to_string()),
+            cidr.add_index(&["nmae"])
+        );
+    }
+
+    #[test]
+    fn index_bytes_scale_with_networks() {
+        let cidr = |rows: u32| {
+            Cidr::new(
+                (0..rows)
+                    .map(|row| vec![format!("10.{}.{}.0/24", row / 256, row % 256)])
+                    .collect(),
+                vec!["cidr".to_string()],
+                "cidr",
+            )
+            .unwrap()
+        };
+
+        let small = cidr(10).index_bytes();
+        let large = cidr(1000).index_bytes();
+        assert_eq!(1, small.len());
+        assert!(large[0] > small[0] * 10);
+    }
+
+    #[test]
+    fn rejects_invalid_network() {
+        assert_eq!(
+            Err("invalid network 'nope' in row 2".to_string()),
+            Cidr::new(
+                vec![vec!["10.0.0.0/8".to_string()], vec!["nope".to_string()]],
+                vec!["cidr".to_string()],
+                "cidr",
+            )
+            .map(|_| ())
+        );
+    }
+}
@@ -1,10 +1,23 @@
+use super::background::{Background, WhileLoading};
+use super::cache::CacheConfig;
+use super::normalize::Normalize;
+use super::partitioned::Partitioned;
+use super::schema::{Schema, SchemaConfig};
+use super::util::{nearest_rank, parse_number};
 use crate::config::{EnrichmentTableConfig, EnrichmentTableDescription};
+use flate2::read::MultiGzDecoder;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::hash::Hasher;
+use std::io::{BufReader, Read};
+use std::mem::size_of;
 use std::path::PathBuf;
 use tracing::trace;
-use vector_core::enrichment::{Condition, IndexHandle, Table};
+use vector_core::enrichment::{Condition, Direction, Explain, IndexHandle, Search, Table};
+use vrl::Value;
 
 #[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -26,50 +39,185 @@ impl Default for Encoding {
     }
 }
 
-#[derive(Deserialize, Serialize, Default, Debug, Eq, PartialEq, Clone)]
-struct FileC {
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Compression {
+    /// Decompress files with a `.gz` extension, read everything else as is.
+    Auto,
+    None,
+    Gzip,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone)]
+pub(super) struct FileC {
     path: PathBuf,
     encoding: Encoding,
+    #[serde(default)]
+    compression: Compression,
+    /// Trim leading and trailing whitespace from the headers and cells.
+    #[serde(default = "crate::serde::default_true")]
+    trim: bool,
+}
+
+impl Default for FileC {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::default(),
+            encoding: Encoding::default(),
+            compression: Compression::default(),
+            trim: true,
+        }
+    }
+}
+
+/// How lookups behave when more than one row matches.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Lookups error.
+    Error,
+    /// Lookups return the matching row that comes first in the table.
+    First,
+    /// Lookups return the matching row that comes last in the table, which for a file is the one
+    /// latest in the file.
+    Last,
+}
+
+impl Default for DuplicatePolicy {
+    fn default() -> Self {
+        Self::Error
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
 struct FileConfig {
     file: FileC,
+    /// Load the file in the background rather than blocking startup until it has loaded.
+    #[serde(default)]
+    background_load: bool,
+    #[serde(default)]
+    while_loading: WhileLoading,
+    cache: Option<CacheConfig>,
+    #[serde(default)]
+    schema: SchemaConfig,
+    /// Split the table into partitions by the value of this column, each with its own indexes.
+    /// Lookups must then give the partition with an equality condition on the column.
+    partition_by: Option<String>,
+    /// Normalizes the cells and the values of equality conditions before comparing them, in
+    /// addition to ignoring case.
+    #[serde(default)]
+    normalize: Normalize,
+    #[serde(default)]
+    duplicate_policy: DuplicatePolicy,
 }
 
 fn default_delimiter() -> char {
     ','
 }
 
-#[async_trait::async_trait]
-#[typetag::serde(name = "file")]
-impl EnrichmentTableConfig for FileConfig {
-    async fn build(
-        &self,
-        _globals: &crate::config::GlobalOptions,
-    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+impl FileC {
+    /// Removes the byte order mark that some editors write at the start of UTF-8 files, which
+    /// would otherwise become part of the first header or cell.
+    fn strip_bom(&self, cell: Option<&mut String>) {
+        if let Some(cell) = cell {
+            if let Some(stripped) = cell.strip_prefix('\u{feff}') {
+                // Any whitespace following the mark wasn't trimmed by the reader.
+                *cell = if self.trim {
+                    stripped.trim().to_string()
+                } else {
+                    stripped.to_string()
+                };
+            }
+        }
+    }
+
+    fn is_gzip(&self) -> bool {
+        match self.compression {
+            Compression::Auto => self
+                .path
+                .extension()
+                .map_or(false, |extension| extension == "gz"),
+            Compression::None => false,
+            Compression::Gzip => true,
+        }
+    }
+
+    fn reader(&self) -> crate::Result<csv::Reader<Box<dyn Read>>> {
         let Encoding::Csv {
             include_headers,
             delimiter,
-        } = self.file.encoding;
+        } = self.encoding;
+
+        let file = std::fs::File::open(&self.path)?;
+        // Decompress as we read so that the whole compressed file is never held in memory.
+        let file: Box<dyn Read> = if self.is_gzip() {
+            Box::new(MultiGzDecoder::new(BufReader::new(file)))
+        } else {
+            Box::new(file)
+        };
 
-        let mut reader = csv::ReaderBuilder::new()
+        Ok(csv::ReaderBuilder::new()
             .has_headers(include_headers)
             .delimiter(delimiter as u8)
-            .from_path(&self.file.path)?;
+            .trim(if self.trim {
+                csv::Trim::All
+            } else {
+                csv::Trim::None
+            })
+            .from_reader(file))
+    }
+
+    /// Reads the headers of the file without reading the rest of it.
+    pub(super) fn read_headers(&self) -> crate::Result<Vec<String>> {
+        let Encoding::Csv {
+            include_headers, ..
+        } = self.encoding;
+        let mut reader = self.reader()?;
+
+        if include_headers {
+            let mut headers = reader
+                .headers()?
+                .iter()
+                .map(|col| col.to_string())
+                .collect::<Vec<_>>();
+            self.strip_bom(headers.first_mut());
+            Ok(headers)
+        } else {
+            match reader.records().next() {
+                Some(row) => Ok((0..row?.len()).map(|idx| idx.to_string()).collect()),
+                None => Ok(Vec::new()),
+            }
+        }
+    }
+
+    /// Reads the rows and the headers of the file.
+    pub(super) fn read(&self) -> crate::Result<(Vec<Vec<String>>, Vec<String>)> {
+        let Encoding::Csv {
+            include_headers, ..
+        } = self.encoding;
+        let mut reader = self.reader()?;
 
-        let data = reader
+        let mut data = reader
             .records()
             .map(|row| Ok(row?.iter().map(|col| col.to_string()).collect::<Vec<_>>()))
             .collect::<crate::Result<Vec<_>>>()?;
 
         let headers = if include_headers {
-            reader
+            let mut headers = reader
                 .headers()?
                 .iter()
                 .map(|col| col.to_string())
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>();
+            self.strip_bom(headers.first_mut());
+            headers
         } else {
+            self.strip_bom(data.first_mut().and_then(|row| row.first_mut()));
             // If there are no headers in the datafile we make headers as the numerical index of
             // the column.
             match data.get(0) {
@@ -80,11 +228,124 @@ impl EnrichmentTableConfig for FileConfig {
 
         trace!(
             "Loaded enrichment file {} with headers {:?}.",
-            self.file.path.to_str().unwrap_or("path with invalid utf"),
+            self.path.to_str().unwrap_or("path with invalid utf"),
             headers
         );
 
-        Ok(Box::new(File::new(data, headers)))
+        Ok((data, headers))
+    }
+
+    fn load(&self, schema: &SchemaConfig) -> crate::Result<File> {
+        let (data, headers) = self.read()?;
+        let schema = schema.build(&headers, &data)?;
+        Ok(File::new(data, headers).with_schema(schema))
+    }
+}
+
+impl FileConfig {
+    fn cached(
+        &self,
+        table: Box<dyn Table + Send + Sync>,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        Ok(match &self.cache {
+            Some(cache) => cache.wrap(table, &self.normalize)?,
+            None => table,
+        })
+    }
+
+    fn load(&self) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        let field = match &self.partition_by {
+            Some(field) => field,
+            None => {
+                let file = self
+                    .file
+                    .load(&self.schema)?
+                    .with_normalize(self.normalize.clone())
+                    .with_duplicate_policy(self.duplicate_policy);
+                return Ok(Box::new(file));
+            }
+        };
+
+        let (data, headers) = self.file.read()?;
+        let schema = self.schema.build(&headers, &data)?;
+        let column = headers
+            .iter()
+            .position(|header| header == field)
+            .ok_or_else(|| {
+                format!(
+                    "partition field '{}' not in table, available columns are: {}",
+                    field,
+                    headers.join(", ")
+                )
+            })?;
+
+        let mut partitions: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+        for row in data {
+            partitions
+                .entry(self.normalize.key(&row[column]))
+                .or_default()
+                .push(row);
+        }
+
+        let partitions = partitions
+            .into_iter()
+            .map(|(key, rows)| {
+                let file = File::new(rows, headers.clone())
+                    .with_schema(schema.clone())
+                    .with_normalize(self.normalize.clone())
+                    .with_duplicate_policy(self.duplicate_policy);
+                (key, Box::new(file) as Box<dyn Table + Send + Sync>)
+            })
+            .collect();
+
+        Ok(Box::new(Partitioned::new(
+            field.clone(),
+            headers,
+            self.normalize.clone(),
+            partitions,
+        )))
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "file")]
+impl EnrichmentTableConfig for FileConfig {
+    async fn build(
+        &self,
+        name: &str,
+        _globals: &crate::config::GlobalOptions,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        let table: Box<dyn Table + Send + Sync> = if self.background_load {
+            // Only the headers are read up front, so indexes can be checked whilst loading.
+            let columns = self.file.read_headers()?;
+            let config = self.clone();
+            Box::new(Background::spawn(
+                name,
+                self.while_loading,
+                columns,
+                async move { tokio::task::spawn_blocking(move || config.load()).await? },
+            ))
+        } else {
+            self.load()?
+        };
+
+        self.cached(table)
+    }
+
+    /// Loads the table before returning it, even if it would be loaded in the background.
+    async fn rebuild(
+        &self,
+        _name: &str,
+        _globals: &crate::config::GlobalOptions,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        let config = self.clone();
+        let table = tokio::task::spawn_blocking(move || config.load()).await??;
+
+        self.cached(table)
+    }
+
+    fn reloads_data(&self) -> bool {
+        true
     }
 }
 
@@ -94,11 +355,82 @@ inventory::submit! {
 
 impl_generate_config_from_default!(FileConfig);
 
+/// An index over one or more columns, kept in the order they were given to `add_index`.
+///
+/// A lookup giving values for all the indexed columns finds the rows by hashing the values. A
+/// lookup giving values for only the leading columns, for example the first column of an index
+/// on `["a", "b"]` but not the second on its own, searches the rows sorted by their key for the
+/// ones starting with those values. Lookups giving none of the leading columns scan every row.
+///
+/// Operator indexes, added by `add_operator_index`, are on a single column and also hold what the
+/// operators search. A `Condition::Nearest` on the column searches the numbers of the column in
+/// order, and a `Condition::Contains` splitting the sets by `MEMBER_SEPARATOR` looks the value up
+/// amongst the members of the sets in the column.
+///
+/// Rows are added and removed in place, without rebuilding the index.
+#[derive(Clone, Default)]
+struct Index {
+    fields: Vec<usize>,
+    rows: IndexRows,
+    /// The rows by their key, in order, only built for indexes with more than one column.
+    sorted: BTreeSet<(Vec<String>, usize)>,
+    /// Whether this is an operator index, which builds `numbers` and `members`.
+    operators: bool,
+    /// The numbers in the column and their rows, in order, only built for operator indexes. Rows
+    /// that don't hold a number are left out.
+    numbers: BTreeSet<(Number, usize)>,
+    /// The rows by the hash of the normalized key of each member of the set in their cell, only
+    /// built for operator indexes.
+    members: IndexRows,
+}
+
+type IndexRows = HashMap<u64, Vec<usize>, hash_hasher::HashBuildHasher>;
+
+/// The separator of the sets indexed by operator indexes, which is the default separator of
+/// `Condition::Contains`. Lookups using other separators scan the rows.
+const MEMBER_SEPARATOR: char = ',';
+
+/// A number held by a cell, which is never NaN so can be ordered.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+struct Number(f64);
+
+impl Eq for Number {}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.partial_cmp(other).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// How `find_rows` searched for the rows, for `explain`.
+#[derive(Clone, Copy)]
+struct Searched {
+    /// The position of the index that was searched.
+    index: Option<usize>,
+    search: Search,
+    rows_examined: usize,
+}
+
+impl Default for Searched {
+    fn default() -> Self {
+        Self {
+            index: None,
+            search: Search::Scan,
+            rows_examined: 0,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct File {
     data: Vec<Vec<String>>,
     headers: Vec<String>,
-    indexes: Vec<HashMap<u64, Vec<usize>, hash_hasher::HashBuildHasher>>,
+    schema: Schema,
+    normalize: Normalize,
+    duplicates: DuplicatePolicy,
+    /// The columns of each row whose cells are absent rather than empty.
+    absent: HashMap<usize, Vec<usize>>,
+    indexes: Vec<Index>,
 }
 
 impl File {
@@ -106,213 +438,889 @@ impl File {
         Self {
             data,
             headers,
+            schema: Schema::default(),
+            normalize: Normalize::default(),
+            duplicates: DuplicatePolicy::default(),
+            absent: HashMap::new(),
             indexes: Vec::new(),
         }
     }
 
+    /// Sets the types of the columns, which are all strings by default. Equality conditions on
+    /// typed columns compare the parsed values, so this must be set before any indexes are added.
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Sets how the cells and the values of equality conditions are normalized, which only
+    /// lowercases them by default. This must be set before any indexes are added.
+    pub fn with_normalize(mut self, normalize: Normalize) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Sets the columns of each row, by position, whose cells are absent rather than empty, for
+    /// tables whose rows don't all have every column. Absent cells are left out of the rows found
+    /// and no condition matches them, so `Condition::IsEmpty` only matches cells that are empty.
+    pub fn with_absent(mut self, absent: HashMap<usize, Vec<usize>>) -> Self {
+        self.absent = absent;
+        self
+    }
+
+    /// Sets how lookups behave when more than one row matches, which is an error by default.
+    pub fn with_duplicate_policy(mut self, duplicates: DuplicatePolicy) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
     fn column_index(&self, col: &str) -> Option<usize> {
         self.headers.iter().position(|header| header == col)
     }
 
-    fn row_equals(&self, condition: &[Condition], row: &[String]) -> bool {
-        condition.iter().all(|condition| match condition {
-            Condition::Equals { field, value } => match self.column_index(field) {
-                None => false,
-                Some(idx) => row[idx].to_lowercase() == value.to_lowercase(),
-            },
-        })
+    /// Appends the row, adding it to the existing indexes rather than rebuilding them. Returns
+    /// the position of the row.
+    pub(super) fn push_row(&mut self, row: Vec<String>) -> usize {
+        self.data.push(row);
+        let idx = self.data.len() - 1;
+        for index in &mut self.indexes {
+            index.insert(&self.schema, &self.normalize, &self.data[idx], idx);
+        }
+
+        idx
     }
 
-    fn add_columns(&self, row: &[String]) -> BTreeMap<String, String> {
-        self.headers
-            .iter()
-            .zip(row)
-            .map(|(header, col)| (header.clone(), col.clone()))
-            .collect()
+    /// Replaces the row at the given position, updating the existing indexes.
+    pub(super) fn replace_row(&mut self, idx: usize, row: Vec<String>) {
+        for index in &mut self.indexes {
+            index.remove(&self.schema, &self.normalize, &self.data[idx], idx);
+        }
+        self.data[idx] = row;
+        self.absent.remove(&idx);
+        for index in &mut self.indexes {
+            index.insert(&self.schema, &self.normalize, &self.data[idx], idx);
+        }
     }
 
-    /// Creates an index with the given fields.
-    /// Uses seahash to create a hash of the data that is used as the key in a hashmap lookup to
-    /// the index of the row in the data.
-    fn index_data(&self, index: &[&str]) -> HashMap<u64, Vec<usize>, hash_hasher::HashBuildHasher> {
-        // Get the positions of the fields we are indexing
-        let fieldidx = self
-            .headers
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, col)| {
-                if index.contains(&col.as_ref()) {
-                    Some(idx)
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
+    fn row_matches(&self, condition: &[Condition], idx: usize) -> bool {
+        let absent = self.absent.get(&idx);
+        condition.iter().all(|condition| {
+            let is_absent = absent.map_or(false, |absent| {
+                super::util::condition_column(&self.headers, condition)
+                    .map_or(false, |column| absent.contains(&column))
+            });
+            !is_absent
+                && super::util::condition_matches(
+                    &self.headers,
+                    &self.schema,
+                    &self.normalize,
+                    condition,
+                    &self.data[idx],
+                )
+        })
+    }
 
-        let mut index = HashMap::with_capacity_and_hasher(
-            self.data.len(),
-            hash_hasher::HashBuildHasher::default(),
-        );
+    /// Returns the rows found by an index that match the condition. The index may not cover all
+    /// the conditions, so the rows still need checking against them.
+    fn matching_rows(&self, condition: &[Condition], rows: &[usize]) -> Vec<usize> {
+        self.closest(
+            condition,
+            rows.iter()
+                .copied()
+                .filter(|idx| self.row_matches(condition, *idx))
+                .collect(),
+        )
+    }
 
-        for (idx, row) in self.data.iter().enumerate() {
-            let mut hash = seahash::SeaHasher::default();
-            for idx in &fieldidx {
-                hash.write(row[*idx].to_lowercase().as_bytes());
-                hash.write_u8(0);
+    /// Keeps the rows holding the numbers closest to the values of any `Condition::Nearest`.
+    fn closest(&self, condition: &[Condition], mut rows: Vec<usize>) -> Vec<usize> {
+        for condition in condition {
+            if let Condition::Nearest {
+                field,
+                value,
+                direction,
+            } = condition
+            {
+                let idx = match self.column_index(field) {
+                    Some(idx) => idx,
+                    None => return Vec::new(),
+                };
+                let rank = |row: usize| {
+                    parse_number(&self.data[row][idx])
+                        .and_then(|number| nearest_rank(*direction, number, *value))
+                };
+
+                let best = rows
+                    .iter()
+                    .filter_map(|row| rank(*row))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                rows.retain(|row| rank(*row) == best);
             }
+        }
 
-            let key = hash.finish();
+        rows
+    }
 
-            let entry = index.entry(key).or_insert_with(Vec::new);
-            entry.push(idx);
+    /// Finds the rows closest to the value using the numbers of a single column index. Searches
+    /// outwards from the value for the closest numbers either side held by a row that matches the
+    /// rest of the condition.
+    fn index_nearest(
+        &self,
+        index: &Index,
+        condition: &[Condition],
+        value: f64,
+        direction: Direction,
+        examined: &Cell<usize>,
+    ) -> Vec<usize> {
+        if value.is_nan() {
+            return Vec::new();
         }
 
-        index.shrink_to_fit();
+        let numbers = &index.numbers;
+        let matches = |(_, idx): &&(Number, usize)| {
+            examined.set(examined.get() + 1);
+            self.row_matches(condition, *idx)
+        };
+
+        let floor = || {
+            numbers
+                .range(..=(Number(value), usize::MAX))
+                .rev()
+                .find(matches)
+                .map(|(number, _)| *number)
+        };
+        let ceiling = || {
+            numbers
+                .range((Number(value), 0)..)
+                .find(matches)
+                .map(|(number, _)| *number)
+        };
 
-        index
+        let best = match direction {
+            Direction::Floor => floor(),
+            Direction::Ceiling => ceiling(),
+            Direction::Nearest => match (floor(), ceiling()) {
+                // The lower number wins a tie.
+                (Some(floor), Some(ceiling)) if value - floor.0 <= ceiling.0 - value => Some(floor),
+                (Some(_), Some(ceiling)) => Some(ceiling),
+                (floor, ceiling) => floor.or(ceiling),
+            },
+        };
+
+        match best {
+            Some(best) => numbers
+                .range((best, 0)..=(best, usize::MAX))
+                .filter(matches)
+                .map(|(_, idx)| *idx)
+                .collect(),
+            None => Vec::new(),
+        }
     }
-}
 
-impl Table for File {
-    fn find_table_row<'a>(
+    /// Returns the positions of the rows matching the condition, in the order of the table, that
+    /// the duplicate policy allows the lookup to return. Records how the rows were found in
+    /// `searched`.
+    fn find_rows<'a>(
         &self,
         condition: &'a [Condition<'a>],
         index: Option<IndexHandle>,
-    ) -> Result<BTreeMap<String, String>, String> {
-        match index {
+        searched: &mut Searched,
+    ) -> Result<Vec<usize>, String> {
+        let (mut rows, scanned) = match index {
             None => {
                 // No index has been passed so we need to do a Sequential Scan.
-                let mut found = self.data.iter().filter_map(|row| {
-                    if self.row_equals(condition, &*row) {
-                        Some(self.add_columns(row))
-                    } else {
-                        None
-                    }
-                });
-
-                let result = found.next();
+                let mut examined = 0;
+                let found = (0..self.data.len())
+                    .inspect(|_| examined += 1)
+                    .filter(|idx| self.row_matches(condition, *idx));
 
-                if found.next().is_some() {
-                    // More than one row has been found.
-                    Err("more than one row found".to_string())
+                let rows = if condition
+                    .iter()
+                    .any(|condition| matches!(condition, Condition::Nearest { .. }))
+                {
+                    self.closest(condition, found.collect())
+                } else if self.duplicates == DuplicatePolicy::Error {
+                    // Stop once we know there is more than one row.
+                    found.take(2).collect()
                 } else {
-                    result.ok_or_else(|| "no rows found".to_string())
-                }
+                    found.collect()
+                };
+
+                *searched = Searched {
+                    index: None,
+                    search: Search::Scan,
+                    rows_examined: examined,
+                };
+                (rows, true)
             }
             Some(IndexHandle(handle)) => {
                 // The index to use has been passed, we can use this to search the data.
-                // We are assuming that the caller has passed an index that represents the fields
-                // being passed in the condition.
-                let mut hash = seahash::SeaHasher::default();
-
-                for header in self.headers.iter() {
-                    if let Some(Condition::Equals { value, .. }) = condition.iter().find(|condition|
-                    {
-                        matches!(condition, Condition::Equals { field, .. } if field == header)
-                    })
-                    {
-                            hash.write(value.to_lowercase().as_bytes());
-                            hash.write_u8(0);
+                let index = &self.indexes[handle];
+
+                // The numbers of the index are only searched for a lone nearest condition, others
+                // are applied to the rows in the order given by `closest`.
+                let nearest = match condition
+                    .iter()
+                    .filter(|condition| matches!(condition, Condition::Nearest { .. }))
+                    .collect::<Vec<_>>()
+                    .as_slice()
+                {
+                    [Condition::Nearest {
+                        field,
+                        value,
+                        direction,
+                    }] if index.operators && *field == self.headers[index.fields[0]] => {
+                        Some((*value, *direction))
                     }
-                }
+                    _ => None,
+                };
+                let contains = condition.iter().find_map(|condition| match condition {
+                    Condition::Contains {
+                        field,
+                        value,
+                        separator: MEMBER_SEPARATOR,
+                    } if index.operators && *field == self.headers[index.fields[0]] => Some(value),
+                    _ => None,
+                });
 
-                let key = hash.finish();
-
-                self.indexes[handle]
-                    .get(&key)
-                    .ok_or_else(|| "no rows found".to_string())
-                    .and_then(|rows| {
-                        // Ensure we have exactly one result.
-                        if rows.len() == 1 {
-                            Ok(self.add_columns(&self.data[rows[0]]))
-                        } else if rows.is_empty() {
-                            Err("no rows found".to_string())
-                        } else {
-                            Err(format!("{} rows found", rows.len()))
-                        }
+                // Find the values of the equality conditions on the leading indexed columns.
+                let values = index
+                    .fields
+                    .iter()
+                    .map(|idx| {
+                        condition.iter().find_map(|condition| match condition {
+                            Condition::Equals { field, value } if *field == self.headers[*idx] => {
+                                Some(self.schema.key(*idx, &self.normalize, value))
+                            }
+                            _ => None,
+                        })
                     })
+                    .take_while(Option::is_some)
+                    .flatten()
+                    .collect::<Vec<_>>();
+
+                searched.index = Some(handle);
+                let rows = match (nearest, contains, self.index_rows(index, &values)) {
+                    (Some((value, direction)), _, _) => {
+                        let examined = Cell::new(0);
+                        let rows =
+                            self.index_nearest(index, condition, value, direction, &examined);
+                        searched.search = Search::Nearest;
+                        searched.rows_examined = examined.get();
+                        rows
+                    }
+                    (None, Some(value), _) => {
+                        let key = self
+                            .schema
+                            .key(index.fields[0], &self.normalize, value.trim());
+                        let rows = index
+                            .members
+                            .get(&hash_key(std::iter::once(key)))
+                            .map_or(&[][..], Vec::as_slice);
+                        searched.search = Search::Members;
+                        searched.rows_examined = rows.len();
+                        self.matching_rows(condition, rows)
+                    }
+                    (None, None, Some(rows)) => {
+                        searched.search = if values.len() == index.fields.len() {
+                            Search::Hash
+                        } else {
+                            Search::Prefix
+                        };
+                        searched.rows_examined = rows.len();
+                        self.matching_rows(condition, &rows)
+                    }
+                    // The condition doesn't give the first indexed column so the index is no use.
+                    (None, None, None) => return self.find_rows(condition, None, searched),
+                };
+
+                (rows, false)
             }
+        };
+
+        if rows.is_empty() {
+            return Err("no rows found".to_string());
         }
-    }
 
-    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
-        self.indexes.push(self.index_data(fields));
+        // The rows found by an index aren't always in the order of the table.
+        rows.sort_unstable();
+        match self.duplicates {
+            DuplicatePolicy::Error if rows.len() > 1 && scanned => {
+                return Err("more than one row found".to_string())
+            }
+            DuplicatePolicy::Error if rows.len() > 1 => {
+                return Err(format!("{} rows found", rows.len()))
+            }
+            DuplicatePolicy::First => rows.truncate(1),
+            DuplicatePolicy::Last => rows = rows.split_off(rows.len() - 1),
+            DuplicatePolicy::Error => {}
+        }
 
-        // The returned index handle is the position of the index in our list of indexes.
-        Ok(IndexHandle(self.indexes.len() - 1))
+        Ok(rows)
     }
-}
 
-impl std::fmt::Debug for File {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "File {} row(s) {} index(es)",
-            self.data.len(),
-            self.indexes.len()
-        )
+    fn add_columns(&self, idx: usize, select: Option<&[String]>) -> BTreeMap<String, Value> {
+        let mut columns =
+            super::util::row_columns(&self.headers, &self.schema, &self.data[idx], select);
+        for column in self.absent.get(&idx).into_iter().flatten() {
+            columns.remove(&self.headers[*column]);
+        }
+        columns
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use shared::btreemap;
+    /// Creates an index with the given fields, which is an operator index if `operators` is set.
+    /// Uses seahash to create a hash of the data that is used as the key in a hashmap lookup to
+    /// the index of the row in the data.
+    fn index_data(&self, index: &[&str], operators: bool) -> Index {
+        // Get the positions of the fields we are indexing, in the order they were given.
+        let mut index = Index {
+            fields: index
+                .iter()
+                .filter_map(|field| self.column_index(field))
+                .collect(),
+            operators,
+            ..Index::default()
+        };
 
-    #[test]
-    fn seahash() {
-        // Ensure we can separate fields to create a distinct hash.
-        let mut one = seahash::SeaHasher::default();
-        one.write("norknoog".as_bytes());
-        one.write_u8(0);
-        one.write("donk".as_bytes());
+        index.rows.reserve(self.data.len());
+        for (idx, row) in self.data.iter().enumerate() {
+            index.insert(&self.schema, &self.normalize, row, idx);
+        }
+        index.rows.shrink_to_fit();
+        index.members.shrink_to_fit();
 
-        let mut two = seahash::SeaHasher::default();
-        two.write("nork".as_bytes());
-        one.write_u8(0);
-        two.write("noogdonk".as_bytes());
+        index
+    }
 
-        assert_ne!(one.finish(), two.finish());
+    /// Returns the rows that could match a lookup giving the values, which are normalized, for the
+    /// leading columns of the index. Returns `None` if the index can't be used.
+    fn index_rows<'a>(&self, index: &'a Index, values: &[String]) -> Option<Cow<'a, [usize]>> {
+        if values.is_empty() {
+            None
+        } else if values.len() == index.fields.len() {
+            Some(Cow::Borrowed(
+                index
+                    .rows
+                    .get(&hash_key(values.iter()))
+                    .map_or(&[][..], Vec::as_slice),
+            ))
+        } else {
+            // The rows whose key starts with the values follow the values in order.
+            Some(Cow::Owned(
+                index
+                    .sorted
+                    .range((values.to_vec(), 0)..)
+                    .take_while(|(key, _)| key.starts_with(values))
+                    .map(|(_, idx)| *idx)
+                    .collect(),
+            ))
+        }
     }
+}
 
-    #[test]
-    fn finds_row() {
-        let file = File::new(
-            vec![
-                vec!["zip".to_string(), "zup".to_string()],
-                vec!["zirp".to_string(), "zurp".to_string()],
-            ],
-            vec!["field1".to_string(), "field2".to_string()],
-        );
+impl Index {
+    /// Adds the row at the given position to the index.
+    fn insert(&mut self, schema: &Schema, normalize: &Normalize, row: &[String], idx: usize) {
+        self.rows
+            .entry(hash_key(
+                self.fields
+                    .iter()
+                    .map(|field| schema.key(*field, normalize, &row[*field])),
+            ))
+            .or_insert_with(Vec::new)
+            .push(idx);
 
-        let condition = Condition::Equals {
-            field: "field1",
-            value: "zirp".to_string(),
-        };
+        if self.fields.len() > 1 {
+            self.sorted
+                .insert((sort_key(schema, normalize, &self.fields, row), idx));
+        }
 
-        assert_eq!(
-            Ok(btreemap! {
-                "field1" => "zirp",
-                "field2" => "zurp",
-            }),
-            file.find_table_row(&[condition], None)
-        );
+        if let (true, &[field]) = (self.operators, &self.fields[..]) {
+            if let Some(number) = parse_number(&row[field]) {
+                self.numbers.insert((Number(number), idx));
+            }
+
+            for member in super::util::set_members(&row[field], MEMBER_SEPARATOR) {
+                let entry = self
+                    .members
+                    .entry(hash_key(std::iter::once(
+                        schema.key(field, normalize, member),
+                    )))
+                    .or_insert_with(Vec::new);
+                // A member may be repeated in the set.
+                if entry.last() != Some(&idx) {
+                    entry.push(idx);
+                }
+            }
+        }
     }
 
-    #[test]
-    fn finds_row_with_index() {
-        let mut file = File::new(
-            vec![
-                vec!["zip".to_string(), "zup".to_string()],
-                vec!["zirp".to_string(), "zurp".to_string()],
-            ],
-            vec!["field1".to_string(), "field2".to_string()],
+    /// Removes the row at the given position from the index, this must be called before the row
+    /// is changed.
+    fn remove(&mut self, schema: &Schema, normalize: &Normalize, row: &[String], idx: usize) {
+        let key = hash_key(
+            self.fields
+                .iter()
+                .map(|field| schema.key(*field, normalize, &row[*field])),
         );
+        remove_row(&mut self.rows, key, idx);
 
-        let handle = file.add_index(&["field1"]).unwrap();
+        if self.fields.len() > 1 {
+            self.sorted
+                .remove(&(sort_key(schema, normalize, &self.fields, row), idx));
+        }
 
-        let condition = Condition::Equals {
-            field: "field1",
+        if let (true, &[field]) = (self.operators, &self.fields[..]) {
+            if let Some(number) = parse_number(&row[field]) {
+                self.numbers.remove(&(Number(number), idx));
+            }
+
+            for member in super::util::set_members(&row[field], MEMBER_SEPARATOR) {
+                let key = hash_key(std::iter::once(schema.key(field, normalize, member)));
+                remove_row(&mut self.members, key, idx);
+            }
+        }
+    }
+
+    /// Returns the approximate memory used by the index in bytes.
+    fn bytes(&self) -> usize {
+        let sorted = self
+            .sorted
+            .iter()
+            .map(|(key, _)| {
+                size_of::<(Vec<String>, usize)>()
+                    + key.capacity() * size_of::<String>()
+                    + key.iter().map(String::capacity).sum::<usize>()
+            })
+            .sum::<usize>();
+
+        self.fields.capacity() * size_of::<usize>()
+            + index_rows_bytes(&self.rows)
+            + sorted
+            + self.numbers.len() * size_of::<(Number, usize)>()
+            + index_rows_bytes(&self.members)
+    }
+}
+
+/// Removes the row from those with the given hash.
+fn remove_row(rows: &mut IndexRows, key: u64, idx: usize) {
+    if let Some(found) = rows.get_mut(&key) {
+        found.retain(|other| *other != idx);
+        if found.is_empty() {
+            rows.remove(&key);
+        }
+    }
+}
+
+/// Returns the approximate memory used by the rows of an index in bytes.
+fn index_rows_bytes(rows: &IndexRows) -> usize {
+    rows.capacity() * (size_of::<u64>() + size_of::<Vec<usize>>())
+        + rows
+            .values()
+            .map(|rows| rows.capacity() * size_of::<usize>())
+            .sum::<usize>()
+}
+
+/// The key the rows of an index with more than one column are sorted by.
+fn sort_key(
+    schema: &Schema,
+    normalize: &Normalize,
+    fields: &[usize],
+    row: &[String],
+) -> Vec<String> {
+    fields
+        .iter()
+        .map(|field| schema.key(*field, normalize, &row[*field]))
+        .collect()
+}
+
+/// Hashes the normalized values of the indexed columns of a row.
+fn hash_key<I, S>(values: I) -> u64
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut hash = seahash::SeaHasher::default();
+    for value in values {
+        hash.write(value.as_ref().as_bytes());
+        hash.write_u8(0);
+    }
+
+    hash.finish()
+}
+
+impl Table for File {
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        if let Some(select) = select {
+            super::util::validate_fields(&self.headers, select)?;
+        }
+
+        let rows = self.find_rows(condition, index, &mut Searched::default())?;
+        Ok(self.add_columns(rows[0], select))
+    }
+
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        super::util::validate_fields(&self.headers, fields)?;
+        self.indexes.push(self.index_data(fields, false));
+
+        // The returned index handle is the position of the index in our list of indexes.
+        Ok(IndexHandle(self.indexes.len() - 1))
+    }
+
+    /// Adds an index on the field that also holds its numbers, for `Condition::Nearest`, and the
+    /// members of its sets, for `Condition::Contains`.
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        super::util::validate_fields(&self.headers, &[field])?;
+        self.indexes.push(self.index_data(&[field], true));
+
+        Ok(IndexHandle(self.indexes.len() - 1))
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.headers.clone()
+    }
+
+    /// Explains the search even if it errors, for example when more than one row is found.
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        let mut searched = Searched::default();
+        let _ = self.find_rows(condition, index, &mut searched);
+
+        Some(Explain {
+            index: searched.index.map(|index| {
+                self.indexes[index]
+                    .fields
+                    .iter()
+                    .map(|field| self.headers[*field].clone())
+                    .collect()
+            }),
+            search: searched.search,
+            rows_examined: searched.rows_examined,
+        })
+    }
+
+    fn index_bytes(&self) -> Vec<usize> {
+        self.indexes.iter().map(Index::bytes).collect()
+    }
+
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        Box::new((0..self.data.len()).map(move |row| self.add_columns(row, None)))
+    }
+}
+
+impl std::fmt::Debug for File {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "File {} row(s) {} index(es)",
+            self.data.len(),
+            self.indexes.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrichment_tables::normalize::Normalizer;
+    use crate::enrichment_tables::schema::ColumnType;
+    use crate::test_util::temp_dir;
+    use flate2::write::GzEncoder;
+    use quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
+    use shared::btreemap;
+    use std::io::Write;
+    use std::net::IpAddr;
+    use vector_core::enrichment::Glob;
+
+    fn write_file(path: &std::path::Path, gzip: bool) {
+        let data = "field1,field2\nzip,zup\nzirp,zurp\n";
+        let mut file = std::fs::File::create(path).unwrap();
+        if gzip {
+            let mut encoder = GzEncoder::new(file, flate2::Compression::default());
+            encoder.write_all(data.as_bytes()).unwrap();
+            encoder.finish().unwrap();
+        } else {
+            file.write_all(data.as_bytes()).unwrap();
+        }
+    }
+
+    fn load(path: PathBuf, compression: Compression) -> File {
+        FileC {
+            path,
+            compression,
+            ..Default::default()
+        }
+        .load(&SchemaConfig::default())
+        .unwrap()
+    }
+
+    #[test]
+    fn loads_gzipped_file() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let plain = dir.join("data.csv");
+        write_file(&plain, false);
+        let gzipped = dir.join("data.csv.gz");
+        write_file(&gzipped, true);
+        let gzipped_no_extension = dir.join("data.bin");
+        write_file(&gzipped_no_extension, true);
+
+        let condition = Condition::Equals {
+            field: "field1",
+            value: "zirp".to_string(),
+        };
+        let expected =
+            load(plain, Compression::Auto).find_table_row(&[condition.clone()], None, None);
+        assert_eq!(
+            Ok(btreemap! {
+                "field1" => "zirp",
+                "field2" => "zurp",
+            }),
+            expected
+        );
+
+        assert_eq!(
+            expected,
+            load(gzipped, Compression::Auto).find_table_row(&[condition.clone()], None, None)
+        );
+        assert_eq!(
+            expected,
+            load(gzipped_no_extension, Compression::Gzip).find_table_row(&[condition], None, None)
+        );
+    }
+
+    #[test]
+    fn trims_cells_and_byte_order_mark() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.csv");
+        std::fs::write(&path, "\u{feff}host , port\n db.example.com ,  5432\n").unwrap();
+
+        let condition = Condition::Equals {
+            field: "host",
+            value: "db.example.com".to_string(),
+        };
+        assert_eq!(
+            Ok(btreemap! {
+                "host" => "db.example.com",
+                "port" => "5432",
+            }),
+            load(path.clone(), Compression::None).find_table_row(&[condition], None, None)
+        );
+
+        let file = FileC {
+            path,
+            trim: false,
+            ..Default::default()
+        }
+        .load(&SchemaConfig::default())
+        .unwrap();
+        assert_eq!(
+            vec!["host ".to_string(), " port".to_string()],
+            file.columns()
+        );
+        assert_eq!(
+            vec![btreemap! {
+                "host " => " db.example.com ",
+                " port" => "  5432",
+            }],
+            file.iter_rows().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn loads_partitioned_file() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.csv");
+        std::fs::write(&path, "tenant,host\nacme,db\nInitech,web\n").unwrap();
+
+        let mut config = FileConfig {
+            file: FileC {
+                path,
+                ..Default::default()
+            },
+            partition_by: Some("tenant".to_string()),
+            ..Default::default()
+        };
+        let table = config.load().unwrap();
+
+        let condition = vec![
+            Condition::Equals {
+                field: "tenant",
+                value: "initech".to_string(),
+            },
+            Condition::Equals {
+                field: "host",
+                value: "web".to_string(),
+            },
+        ];
+        assert_eq!(
+            Ok(btreemap! {
+                "tenant" => "Initech",
+                "host" => "web",
+            }),
+            table.find_table_row(&condition, None, None)
+        );
+        assert_eq!(2, table.iter_rows().count());
+
+        config.partition_by = Some("missing".to_string());
+        assert_eq!(
+            "partition field 'missing' not in table, available columns are: tenant, host",
+            config.load().unwrap_err().to_string()
+        );
+    }
+
+    #[cfg(all(feature = "sources-generator", feature = "sinks-blackhole"))]
+    #[tokio::test]
+    async fn reloads_changed_file_with_config() {
+        use crate::{
+            config::Config, sinks::blackhole::BlackholeConfig, sources::generator::GeneratorConfig,
+            test_util::start_topology, topology::builder::ENRICHMENT_TABLES,
+        };
+        use vrl::enrichment::TableSetup;
+
+        let dir = temp_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.csv");
+        std::fs::write(&path, "field1,field2\nzip,zup\n").unwrap();
+
+        // Reloading with the same config, as SIGHUP does after only the file has changed.
+        let config = || {
+            let mut config = Config::builder();
+            config.add_enrichment_table(
+                "reloads_changed_file",
+                FileConfig {
+                    file: FileC {
+                        path: path.clone(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            );
+            config.add_source(
+                "in",
+                GeneratorConfig::repeat(vec!["msg".to_string()], usize::MAX, Some(1.0)),
+            );
+            config.add_sink("out", &["in"], BlackholeConfig::default());
+            config.build().unwrap()
+        };
+        let find = || {
+            let condition = Condition::Equals {
+                field: "field1",
+                value: "zirp".to_string(),
+            };
+            ENRICHMENT_TABLES.as_readonly().find_table_row(
+                "reloads_changed_file",
+                &[condition],
+                None,
+                None,
+            )
+        };
+
+        let (mut topology, _crash) = start_topology(config(), false).await;
+        assert_eq!(Err("no rows found".to_string()), find());
+
+        std::fs::write(&path, "field1,field2\nzip,zup\nzirp,zurp\n").unwrap();
+        assert!(topology.reload_config_and_respawn(config()).await.unwrap());
+        assert_eq!(
+            Ok(btreemap! {
+                "field1" => "zirp",
+                "field2" => "zurp",
+            }),
+            find()
+        );
+
+        // A file that can't be read keeps the current data, without failing the reload.
+        std::fs::remove_file(&path).unwrap();
+        assert!(topology.reload_config_and_respawn(config()).await.unwrap());
+        assert_eq!(
+            Ok(btreemap! {
+                "field1" => "zirp",
+                "field2" => "zurp",
+            }),
+            find()
+        );
+
+        topology.stop().await;
+    }
+
+    #[test]
+    fn seahash() {
+        // Ensure we can separate fields to create a distinct hash.
+        let mut one = seahash::SeaHasher::default();
+        one.write("norknoog".as_bytes());
+        one.write_u8(0);
+        one.write("donk".as_bytes());
+
+        let mut two = seahash::SeaHasher::default();
+        two.write("nork".as_bytes());
+        one.write_u8(0);
+        two.write("noogdonk".as_bytes());
+
+        assert_ne!(one.finish(), two.finish());
+    }
+
+    #[test]
+    fn reports_columns() {
+        let file = File::new(
+            vec![vec!["zip".to_string(), "zup".to_string()]],
+            vec!["field1".to_string(), "field2".to_string()],
+        );
+
+        assert_eq!(
+            vec!["field1".to_string(), "field2".to_string()],
+            file.columns()
+        );
+    }
+
+    #[test]
+    fn finds_row() {
+        let file = File::new(
+            vec![
+                vec!["zip".to_string(), "zup".to_string()],
+                vec!["zirp".to_string(), "zurp".to_string()],
+            ],
+            vec!["field1".to_string(), "field2".to_string()],
+        );
+
+        let condition = Condition::Equals {
+            field: "field1",
+            value: "zirp".to_string(),
+        };
+
+        assert_eq!(
+            Ok(btreemap! {
+                "field1" => "zirp",
+                "field2" => "zurp",
+            }),
+            file.find_table_row(&[condition], None, None)
+        );
+    }
+
+    #[test]
+    fn finds_row_with_index() {
+        let mut file = File::new(
+            vec![
+                vec!["zip".to_string(), "zup".to_string()],
+                vec!["zirp".to_string(), "zurp".to_string()],
+            ],
+            vec!["field1".to_string(), "field2".to_string()],
+        );
+
+        let handle = file.add_index(&["field1"]).unwrap();
+
+        let condition = Condition::Equals {
+            field: "field1",
             value: "zirp".to_string(),
         };
 
@@ -321,7 +1329,882 @@ mod tests {
                 "field1" => "zirp",
                 "field2" => "zurp",
             }),
-            file.find_table_row(&[condition], Some(handle))
+            file.find_table_row(&[condition], None, Some(handle))
+        );
+    }
+
+    #[test]
+    fn explains_lookups() {
+        let mut file = File::new(
+            vec![
+                vec!["zip".to_string(), "zup".to_string()],
+                vec!["zirp".to_string(), "zurp".to_string()],
+                vec!["zorp".to_string(), "zurp".to_string()],
+            ],
+            vec!["field1".to_string(), "field2".to_string()],
+        );
+        let handle = file.add_index(&["field1"]).unwrap();
+
+        let equals = Condition::Equals {
+            field: "field1",
+            value: "zirp".to_string(),
+        };
+        assert_eq!(
+            Some(Explain {
+                index: Some(vec!["field1".to_string()]),
+                search: Search::Hash,
+                rows_examined: 1,
+            }),
+            file.explain(&[equals], Some(handle))
+        );
+
+        // The index can't be used for a glob, so every row is scanned.
+        let glob = Condition::Glob {
+            field: "field1",
+            pattern: Glob::new("z*p"),
+        };
+        assert_eq!(
+            Some(Explain {
+                index: None,
+                search: Search::Scan,
+                rows_examined: 3,
+            }),
+            file.explain(&[glob], Some(handle))
+        );
+    }
+
+    fn composite() -> (File, IndexHandle) {
+        let mut file = File::new(
+            vec![
+                vec!["eu".to_string(), "db".to_string(), "1".to_string()],
+                vec!["eu".to_string(), "web".to_string(), "2".to_string()],
+                vec!["us".to_string(), "db".to_string(), "3".to_string()],
+                vec!["ap".to_string(), "cache".to_string(), "4".to_string()],
+            ],
+            vec!["region".to_string(), "role".to_string(), "id".to_string()],
+        );
+
+        // The index is ordered by role then region, unlike the columns.
+        let handle = file.add_index(&["role", "region"]).unwrap();
+        (file, handle)
+    }
+
+    #[test]
+    fn finds_row_with_composite_index() {
+        let (file, handle) = composite();
+        let condition = vec![
+            Condition::Equals {
+                field: "region",
+                value: "US".to_string(),
+            },
+            Condition::Equals {
+                field: "role",
+                value: "db".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            Ok(btreemap! { "region" => "us", "role" => "db", "id" => "3" }),
+            file.find_table_row(&condition, None, None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "region" => "us", "role" => "db", "id" => "3" }),
+            file.find_table_row(&condition, None, Some(handle))
+        );
+    }
+
+    #[test]
+    fn finds_row_with_index_prefix() {
+        let (file, handle) = composite();
+        let role = |value: &str| Condition::Equals {
+            field: "role",
+            value: value.to_string(),
+        };
+
+        assert_eq!(
+            Ok(btreemap! { "region" => "ap", "role" => "cache", "id" => "4" }),
+            file.find_table_row(&[role("cache")], None, Some(handle))
+        );
+        assert_eq!(
+            Err("2 rows found".to_string()),
+            file.find_table_row(&[role("db")], None, Some(handle))
+        );
+        assert_eq!(
+            Err("no rows found".to_string()),
+            file.find_table_row(&[role("queue")], None, Some(handle))
+        );
+
+        // The prefix narrows the rows that other conditions are checked against.
+        let condition = vec![
+            role("db"),
+            Condition::Equals {
+                field: "id",
+                value: "1".to_string(),
+            },
+        ];
+        assert_eq!(
+            Ok(btreemap! { "region" => "eu", "role" => "db", "id" => "1" }),
+            file.find_table_row(&condition, None, Some(handle))
+        );
+
+        // Without the leading column the index can't be used and all the rows are scanned.
+        let region = Condition::Equals {
+            field: "region",
+            value: "ap".to_string(),
+        };
+        assert_eq!(
+            Ok(btreemap! { "region" => "ap", "role" => "cache", "id" => "4" }),
+            file.find_table_row(&[region], None, Some(handle))
+        );
+    }
+
+    #[test]
+    fn finds_row_with_ip_in_cidr() {
+        let mut file = File::new(
+            vec![
+                vec!["10.0.0.0/8".to_string(), "private".to_string()],
+                vec!["192.0.2.0/24".to_string(), "documentation".to_string()],
+            ],
+            vec!["network".to_string(), "name".to_string()],
+        );
+
+        let condition = Condition::IpInCidr {
+            field: "network",
+            ip: "192.0.2.10".parse().unwrap(),
+        };
+
+        assert_eq!(
+            Ok(btreemap! {
+                "network" => "192.0.2.0/24",
+                "name" => "documentation",
+            }),
+            file.find_table_row(&[condition.clone()], None, None)
+        );
+
+        // Indexes only cover equality, so the network still has to be checked.
+        let handle = file.add_index(&["name"]).unwrap();
+        let condition = vec![
+            condition,
+            Condition::Equals {
+                field: "name",
+                value: "private".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            Err("no rows found".to_string()),
+            file.find_table_row(&condition, None, Some(handle))
+        );
+    }
+
+    #[test]
+    fn finds_row_with_glob() {
+        let mut file = File::new(
+            vec![
+                vec!["prod".to_string(), "*.internal.example.com".to_string()],
+                vec!["prod".to_string(), "*.example.com".to_string()],
+                vec!["dev".to_string(), "*.internal.example.com".to_string()],
+            ],
+            vec!["env".to_string(), "pattern".to_string()],
+        );
+        let hosts = File::new(
+            vec![
+                vec!["db.internal.example.com".to_string()],
+                vec!["web.example.com".to_string()],
+            ],
+            vec!["host".to_string()],
+        );
+
+        let glob = |pattern: &str| Condition::Glob {
+            field: "host",
+            pattern: Glob::new(pattern),
+        };
+
+        assert_eq!(
+            Ok(btreemap! { "host" => "db.internal.example.com" }),
+            hosts.find_table_row(&[glob("*.internal.*")], None, None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "host" => "web.example.com" }),
+            hosts.find_table_row(&[glob("web*")], None, None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "host" => "db.internal.example.com" }),
+            hosts.find_table_row(&[glob("db*.com")], None, None)
+        );
+        assert_eq!(
+            Err("more than one row found".to_string()),
+            hosts.find_table_row(&[glob("*.example.com")], None, None)
+        );
+        assert_eq!(
+            Err("no rows found".to_string()),
+            hosts.find_table_row(&[glob("*.internal")], None, None)
+        );
+
+        // The glob is checked against the rows found by an index on the other conditions.
+        let handle = file.add_index(&["env"]).unwrap();
+        let condition = vec![
+            Condition::Equals {
+                field: "env",
+                value: "prod".to_string(),
+            },
+            Condition::Glob {
+                field: "pattern",
+                pattern: Glob::new(r"\*.example.com"),
+            },
+        ];
+        assert_eq!(
+            Ok(btreemap! { "env" => "prod", "pattern" => "*.example.com" }),
+            file.find_table_row(&condition, None, Some(handle))
+        );
+    }
+
+    #[test]
+    fn finds_row_with_normalized_keys() {
+        let file = |normalize: Vec<Normalizer>| {
+            File::new(
+                vec![
+                    vec!["Zurich".to_string(), "CH".to_string()],
+                    vec!["Malmo".to_string(), "SE".to_string()],
+                ],
+                vec!["city".to_string(), "country".to_string()],
+            )
+            .with_normalize(Normalize::new(normalize))
+        };
+        let condition = |city: &str| {
+            vec![
+                Condition::Equals {
+                    field: "city",
+                    value: city.to_string(),
+                },
+                Condition::Equals {
+                    field: "country",
+                    value: "ch".to_string(),
+                },
+            ]
+        };
+
+        let mut stripped = file(vec![Normalizer::Trim, Normalizer::StripAccents]);
+        let mut plain = file(Vec::new());
+        let stripped_handles = vec![
+            None,
+            Some(stripped.add_index(&["city"]).unwrap()),
+            Some(stripped.add_index(&["city", "country"]).unwrap()),
+        ];
+        let plain_handles = vec![None, Some(plain.add_index(&["city"]).unwrap())];
+
+        for index in stripped_handles {
+            assert_eq!(
+                Ok(btreemap! { "city" => "Zurich", "country" => "CH" }),
+                stripped.find_table_row(&condition(" Z\u{fc}rich "), None, index)
+            );
+            // Searches the rows of the composite index starting with the city.
+            assert_eq!(
+                Ok(btreemap! { "city" => "Zurich", "country" => "CH" }),
+                stripped.find_table_row(&condition(" Z\u{fc}rich ")[..1], None, index)
+            );
+        }
+        for index in plain_handles {
+            assert_eq!(
+                Err("no rows found".to_string()),
+                plain.find_table_row(&condition(" Z\u{fc}rich "), None, index)
+            );
+        }
+    }
+
+    #[test]
+    fn finds_rows_by_duplicate_policy() {
+        let file = |duplicates| {
+            let mut file = File::new(
+                vec![
+                    vec!["db".to_string(), "1".to_string()],
+                    vec!["web".to_string(), "2".to_string()],
+                    vec!["db".to_string(), "3".to_string()],
+                ],
+                vec!["host".to_string(), "owner".to_string()],
+            )
+            .with_duplicate_policy(duplicates);
+            let handle = file.add_index(&["host"]).unwrap();
+
+            // The replaced row is added to the end of the index, but is still first in the table.
+            file.push_row(vec!["db".to_string(), "4".to_string()]);
+            file.replace_row(0, vec!["db".to_string(), "5".to_string()]);
+            (file, handle)
+        };
+        let condition = [Condition::Equals {
+            field: "host",
+            value: "db".to_string(),
+        }];
+
+        let (error, handle) = file(DuplicatePolicy::Error);
+        assert_eq!(
+            Err("more than one row found".to_string()),
+            error.find_table_row(&condition, None, None)
+        );
+        assert_eq!(
+            Err("3 rows found".to_string()),
+            error.find_table_row(&condition, None, Some(handle))
+        );
+
+        for index in [None, Some(handle)].iter().copied() {
+            let (first, _) = file(DuplicatePolicy::First);
+            assert_eq!(
+                Ok(btreemap! { "host" => "db", "owner" => "5" }),
+                first.find_table_row(&condition, None, index)
+            );
+
+            let (last, _) = file(DuplicatePolicy::Last);
+            assert_eq!(
+                Ok(btreemap! { "host" => "db", "owner" => "4" }),
+                last.find_table_row(&condition, None, index)
+            );
+        }
+    }
+
+    #[test]
+    fn index_bytes_scale_with_rows_and_indexes() {
+        let file = |rows: usize| {
+            File::new(
+                (0..rows)
+                    .map(|row| {
+                        vec![
+                            row.to_string(),
+                            format!("host-{}", row),
+                            format!("a-{0},b-{0},c-{0}", row),
+                        ]
+                    })
+                    .collect(),
+                vec!["id".to_string(), "host".to_string(), "tags".to_string()],
+            )
+        };
+
+        let mut small = file(10);
+        let mut large = file(1000);
+        assert!(small.index_bytes().is_empty());
+
+        small.add_index(&["host"]).unwrap();
+        large.add_index(&["host"]).unwrap();
+        assert!(large.index_bytes()[0] > small.index_bytes()[0] * 10);
+
+        // An index with more than one column also holds the rows in order.
+        large.add_index(&["host", "id"]).unwrap();
+        let bytes = large.index_bytes();
+        assert_eq!(2, bytes.len());
+        assert!(bytes[1] > bytes[0]);
+
+        // An operator index on a column holding sets also holds the rows of each member.
+        large.add_index(&["tags"]).unwrap();
+        large.add_operator_index("tags").unwrap();
+        let bytes = large.index_bytes();
+        assert_eq!(4, bytes.len());
+        assert!(bytes[3] > bytes[2] * 2);
+    }
+
+    /// A random table and condition, for checking that lookups using indexes find the same rows
+    /// as scans.
+    #[derive(Clone, Debug)]
+    struct Case {
+        rows: Vec<Vec<String>>,
+        condition: Vec<Condition<'static>>,
+    }
+
+    const COLUMNS: [&str; 3] = ["a", "b", "c"];
+
+    const CELLS: [&str; 12] = [
+        "",
+        "x",
+        "X",
+        "y",
+        "1",
+        "-1",
+        "1.5",
+        "10",
+        "x,y",
+        " y , z ",
+        "10.0.0.0/8",
+        "10.1.0.0/16",
+    ];
+
+    impl Arbitrary for Case {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let cell = |g: &mut Gen| g.choose(&CELLS).unwrap().to_string();
+
+            let rows = (0..usize::arbitrary(g) % 12)
+                .map(|_| COLUMNS.iter().map(|_| cell(g)).collect())
+                .collect();
+
+            let condition = (0..1 + usize::arbitrary(g) % 3)
+                .map(|_| {
+                    let field = *g.choose(&COLUMNS).unwrap();
+                    match usize::arbitrary(g) % 7 {
+                        0 => Condition::Equals {
+                            field,
+                            value: cell(g),
+                        },
+                        1 => Condition::IpInCidr {
+                            field,
+                            ip: *g
+                                .choose(&[
+                                    "10.1.2.3".parse::<IpAddr>().unwrap(),
+                                    "::1".parse().unwrap(),
+                                ])
+                                .unwrap(),
+                        },
+                        2 => Condition::Glob {
+                            field,
+                            pattern: Glob::new(g.choose(&["*", "x*", "?", "*y*", "1?"]).unwrap()),
+                        },
+                        3 => Condition::Contains {
+                            field,
+                            value: cell(g),
+                            separator: ',',
+                        },
+                        4 => Condition::IsEmpty { field },
+                        5 => Condition::IsPresent { field },
+                        _ => Condition::Nearest {
+                            field,
+                            value: *g.choose(&[-5.0, 0.0, 1.0, 1.25, 5.5, 10.0, 20.0]).unwrap(),
+                            direction: *g
+                                .choose(&[Direction::Nearest, Direction::Floor, Direction::Ceiling])
+                                .unwrap(),
+                        },
+                    }
+                })
+                .collect();
+
+            Self { rows, condition }
+        }
+    }
+
+    /// The result of a lookup, ignoring the differing errors when more than one row is found.
+    fn outcome(
+        result: Result<BTreeMap<String, Value>, String>,
+    ) -> Result<BTreeMap<String, Value>, bool> {
+        result.map_err(|error| error != "no rows found")
+    }
+
+    #[test]
+    fn indexes_agree_with_scan() {
+        fn inner(case: Case) -> TestResult {
+            let headers = COLUMNS.iter().map(|column| column.to_string()).collect();
+            let indexes: [&[&str]; 5] = [&["a"], &["b"], &["a", "b"], &["b", "c", "a"], &["c"]];
+
+            let mut file = File::new(case.rows.clone(), headers);
+            // Build the indexes before the rows are added, so they are updated row by row.
+            let mut incremental = File::new(Vec::new(), file.columns());
+            for fields in indexes.iter() {
+                file.add_index(fields).unwrap();
+                incremental.add_index(fields).unwrap();
+            }
+            for field in COLUMNS.iter() {
+                file.add_operator_index(field).unwrap();
+                incremental.add_operator_index(field).unwrap();
+            }
+            for row in case.rows.iter().cloned() {
+                incremental.push_row(row);
+            }
+
+            let scan = outcome(file.find_table_row(&case.condition, None, None));
+            let operators = COLUMNS.iter().map(|field| std::slice::from_ref(field));
+            for (handle, fields) in indexes.iter().copied().chain(operators).enumerate() {
+                for table in [&file, &incremental].iter() {
+                    let indexed =
+                        table.find_table_row(&case.condition, None, Some(IndexHandle(handle)));
+                    assert_eq!(scan, outcome(indexed), "index {:?}", fields);
+                }
+            }
+
+            TestResult::passed()
+        }
+
+        QuickCheck::new().quickcheck(inner as fn(Case) -> TestResult);
+    }
+
+    #[test]
+    fn finds_row_with_empty_cells() {
+        let mut file = File::new(
+            vec![
+                vec!["alice".to_string(), "alice@example.com".to_string()],
+                vec!["bob".to_string(), "".to_string()],
+            ],
+            vec!["name".to_string(), "email".to_string()],
+        );
+        let handle = file.add_index(&["email"]).unwrap();
+
+        let bob = Ok(btreemap! { "name" => "bob", "email" => "" });
+        let alice = Ok(btreemap! { "name" => "alice", "email" => "alice@example.com" });
+
+        for index in [None, Some(handle)].iter().copied() {
+            // An empty cell is returned, and equals an empty value.
+            let condition = Condition::Equals {
+                field: "email",
+                value: "".to_string(),
+            };
+            assert_eq!(bob, file.find_table_row(&[condition], None, index));
+
+            let condition = Condition::IsEmpty { field: "email" };
+            assert_eq!(bob, file.find_table_row(&[condition], None, index));
+
+            let condition = Condition::IsPresent { field: "email" };
+            assert_eq!(alice, file.find_table_row(&[condition], None, index));
+        }
+
+        // A column that isn't in the table is neither empty nor present.
+        for condition in vec![
+            Condition::IsEmpty { field: "phone" },
+            Condition::IsPresent { field: "phone" },
+            Condition::Equals {
+                field: "phone",
+                value: "".to_string(),
+            },
+        ] {
+            assert_eq!(
+                Err("no rows found".to_string()),
+                file.find_table_row(&[condition], None, None)
+            );
+        }
+    }
+
+    #[test]
+    fn finds_row_with_contains() {
+        let mut file = File::new(
+            vec![
+                vec!["db1".to_string(), "db, database , primary".to_string()],
+                vec!["web1".to_string(), "web,www,".to_string()],
+                vec!["none".to_string(), "".to_string()],
+                vec!["repeated".to_string(), "x,x".to_string()],
+                vec!["mail1".to_string(), "mail;smtp".to_string()],
+            ],
+            vec!["host".to_string(), "aliases".to_string()],
+        );
+        let select = vec!["host".to_string()];
+        let handle = file.add_operator_index("aliases").unwrap();
+
+        for index in [None, Some(handle)].iter().copied() {
+            let find = |value: &str, separator: char| {
+                let condition = Condition::Contains {
+                    field: "aliases",
+                    value: value.to_string(),
+                    separator,
+                };
+                file.find_table_row(&[condition], Some(&select), index)
+            };
+            let host = |host: &str| Ok(btreemap! { "host" => host });
+
+            assert_eq!(host("db1"), find("database", ','));
+            assert_eq!(host("db1"), find("PRIMARY", ','));
+            assert_eq!(host("web1"), find(" www ", ','));
+            assert_eq!(host("repeated"), find("x", ','));
+            assert_eq!(Err("no rows found".to_string()), find("data", ','));
+            assert_eq!(Err("no rows found".to_string()), find("", ','));
+            assert_eq!(Err("no rows found".to_string()), find("web", ';'));
+            assert_eq!(host("mail1"), find("smtp", ';'));
+        }
+
+        // Only the sets split by commas are indexed, others are scanned.
+        let explain = |separator: char| {
+            let condition = Condition::Contains {
+                field: "aliases",
+                value: "smtp".to_string(),
+                separator,
+            };
+            file.explain(&[condition], Some(handle))
+                .map(|explain| explain.search)
+        };
+        assert_eq!(Some(Search::Members), explain(','));
+        assert_eq!(Some(Search::Scan), explain(';'));
+
+        // Indexes for equality don't hold the members.
+        let equals = file.add_index(&["aliases"]).unwrap();
+        let condition = Condition::Contains {
+            field: "aliases",
+            value: "smtp".to_string(),
+            separator: ',',
+        };
+        assert_eq!(
+            Some(Search::Scan),
+            file.explain(&[condition], Some(equals))
+                .map(|explain| explain.search)
+        );
+    }
+
+    #[test]
+    fn contains_compares_members_as_equals() {
+        let mut file = File::new(
+            vec![
+                vec!["cafe".to_string(), "Café, bar".to_string()],
+                vec!["pub".to_string(), "inn".to_string()],
+            ],
+            vec!["name".to_string(), "kinds".to_string()],
+        )
+        .with_normalize(Normalize::new(vec![Normalizer::StripAccents]));
+        let select = vec!["name".to_string()];
+        let handle = file.add_operator_index("kinds").unwrap();
+
+        for index in [None, Some(handle)].iter().copied() {
+            let condition = Condition::Contains {
+                field: "kinds",
+                value: " CAFE ".to_string(),
+                separator: ',',
+            };
+            assert_eq!(
+                Ok(btreemap! { "name" => "cafe" }),
+                file.find_table_row(&[condition], Some(&select), index)
+            );
+        }
+    }
+
+    #[test]
+    fn finds_row_with_nearest() {
+        let mut file = File::new(
+            vec![
+                vec!["api".to_string(), "0".to_string(), "fast".to_string()],
+                vec!["api".to_string(), "100".to_string(), "ok".to_string()],
+                vec!["api".to_string(), "500".to_string(), "slow".to_string()],
+                vec!["api".to_string(), "n/a".to_string(), "unknown".to_string()],
+                vec!["web".to_string(), "0".to_string(), "fast".to_string()],
+                vec!["web".to_string(), "250".to_string(), "slow".to_string()],
+            ],
+            vec![
+                "service".to_string(),
+                "threshold".to_string(),
+                "label".to_string(),
+            ],
+        );
+        let select = vec!["label".to_string()];
+
+        let condition = |service: &str, value: f64, direction: Direction| {
+            vec![
+                Condition::Equals {
+                    field: "service",
+                    value: service.to_string(),
+                },
+                Condition::Nearest {
+                    field: "threshold",
+                    value,
+                    direction,
+                },
+            ]
+        };
+
+        let threshold = file.add_operator_index("threshold").unwrap();
+        let service = file.add_index(&["service"]).unwrap();
+
+        for index in [None, Some(threshold), Some(service)].iter().copied() {
+            let find = |service: &str, value: f64, direction: Direction| {
+                file.find_table_row(&condition(service, value, direction), Some(&select), index)
+            };
+            let label = |label: &str| Ok(btreemap! { "label" => label });
+
+            assert_eq!(label("ok"), find("api", 120.0, Direction::Floor));
+            assert_eq!(label("slow"), find("api", 120.0, Direction::Ceiling));
+            assert_eq!(label("ok"), find("api", 120.0, Direction::Nearest));
+            assert_eq!(label("slow"), find("web", 120.0, Direction::Ceiling));
+            assert_eq!(label("fast"), find("web", 120.0, Direction::Floor));
+
+            // An exact match is found in every direction.
+            assert_eq!(label("ok"), find("api", 100.0, Direction::Floor));
+            assert_eq!(label("ok"), find("api", 100.0, Direction::Ceiling));
+            assert_eq!(label("ok"), find("api", 100.0, Direction::Nearest));
+
+            // The lower number wins a tie.
+            assert_eq!(label("ok"), find("api", 300.0, Direction::Nearest));
+
+            // Values out of range find no rows, other than the nearest number.
+            assert_eq!(
+                Err("no rows found".to_string()),
+                find("api", -5.0, Direction::Floor)
+            );
+            assert_eq!(
+                Err("no rows found".to_string()),
+                find("api", 1000.0, Direction::Ceiling)
+            );
+            assert_eq!(label("fast"), find("api", -5.0, Direction::Nearest));
+            assert_eq!(label("slow"), find("api", 1000.0, Direction::Nearest));
+            assert_eq!(
+                Err("no rows found".to_string()),
+                find("api", f64::NAN, Direction::Nearest)
+            );
+        }
+
+        // Both services have a row with the nearest number.
+        let nearest = vec![Condition::Nearest {
+            field: "threshold",
+            value: 50.0,
+            direction: Direction::Floor,
+        }];
+        assert_eq!(
+            Err("more than one row found".to_string()),
+            file.find_table_row(&nearest, None, None)
+        );
+        assert_eq!(
+            Err("2 rows found".to_string()),
+            file.find_table_row(&nearest, None, Some(threshold))
+        );
+    }
+
+    #[test]
+    fn selects_columns() {
+        let mut file = File::new(
+            vec![
+                vec!["zip".to_string(), "zup".to_string(), "zop".to_string()],
+                vec!["zirp".to_string(), "zurp".to_string(), "zorp".to_string()],
+            ],
+            vec![
+                "field1".to_string(),
+                "field2".to_string(),
+                "field3".to_string(),
+            ],
+        );
+        let handle = file.add_index(&["field1"]).unwrap();
+
+        let condition = Condition::Equals {
+            field: "field1",
+            value: "zirp".to_string(),
+        };
+        let select = vec!["field3".to_string()];
+
+        assert_eq!(
+            Ok(btreemap! { "field3" => "zorp" }),
+            file.find_table_row(&[condition.clone()], Some(&select), None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "field3" => "zorp" }),
+            file.find_table_row(&[condition.clone()], Some(&select), Some(handle))
+        );
+        assert_eq!(
+            Err(
+                "field(s) 'field4' not in table, available columns are: field1, field2, field3"
+                    .to_string()
+            ),
+            file.find_table_row(&[condition], Some(&["field4".to_string()]), None)
+        );
+    }
+
+    #[test]
+    fn returns_typed_columns() {
+        let headers = vec!["name".to_string(), "count".to_string(), "ratio".to_string()];
+        let data = vec![
+            vec!["zip".to_string(), "3".to_string(), "0.5".to_string()],
+            vec!["zirp".to_string(), "12".to_string(), "1".to_string()],
+        ];
+        let schema = SchemaConfig {
+            types: vec![
+                ("count".to_string(), ColumnType::Integer),
+                ("ratio".to_string(), ColumnType::Float),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        }
+        .build(&headers, &data)
+        .unwrap();
+        let file = File::new(data, headers).with_schema(schema);
+
+        let condition = Condition::Equals {
+            field: "count",
+            value: "12".to_string(),
+        };
+
+        assert_eq!(
+            Ok(btreemap! {
+                "name" => "zirp",
+                "count" => 12,
+                "ratio" => 1.0,
+            }),
+            file.find_table_row(&[condition], None, None)
+        );
+    }
+
+    #[test]
+    fn compares_typed_columns_by_value() {
+        let headers = vec!["name".to_string(), "count".to_string(), "ratio".to_string()];
+        let data = vec![
+            vec!["zip".to_string(), "3".to_string(), "0.5".to_string()],
+            vec!["zirp".to_string(), "12".to_string(), "1".to_string()],
+        ];
+        let schema = SchemaConfig {
+            types: vec![
+                ("count".to_string(), ColumnType::Integer),
+                ("ratio".to_string(), ColumnType::Float),
+            ]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        }
+        .build(&headers, &data)
+        .unwrap();
+        let mut file = File::new(data, headers).with_schema(schema);
+        let handle = file.add_index(&["count"]).unwrap();
+
+        let find = |field: &'static str, value: &str, index: Option<IndexHandle>| {
+            let condition = Condition::Equals {
+                field,
+                value: value.to_string(),
+            };
+            file.find_table_row(&[condition], Some(&["name".to_string()]), index)
+        };
+
+        for value in &["12", "012", "12.0"] {
+            assert_eq!(
+                Ok(btreemap! { "name" => "zirp" }),
+                find("count", value, None)
+            );
+            assert_eq!(
+                Ok(btreemap! { "name" => "zirp" }),
+                find("count", value, Some(handle))
+            );
+        }
+        assert_eq!(
+            Ok(btreemap! { "name" => "zip" }),
+            find("ratio", "0.50", None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "name" => "zirp" }),
+            find("ratio", "1.0", None)
+        );
+    }
+
+    #[test]
+    fn iterates_rows() {
+        let file = File::new(
+            vec![
+                vec!["zip".to_string(), "zup".to_string()],
+                vec!["zirp".to_string(), "zurp".to_string()],
+            ],
+            vec!["field1".to_string(), "field2".to_string()],
+        );
+
+        assert_eq!(
+            vec![
+                btreemap! {
+                    "field1" => "zip",
+                    "field2" => "zup",
+                },
+                btreemap! {
+                    "field1" => "zirp",
+                    "field2" => "zurp",
+                },
+            ],
+            file.iter_rows().collect::<Vec<_>>()
+        );
+        assert_eq!(1, file.iter_rows().take(1).count());
+    }
+
+    #[test]
+    fn add_index_validates_fields() {
+        let mut file = File::new(
+            vec![vec!["zip".to_string(), "zup".to_string()]],
+            vec!["field1".to_string(), "field2".to_string()],
+        );
+
+        assert_eq!(Ok(IndexHandle(0)), file.add_index(&["field1", "field2"]));
+        assert_eq!(
+            Err(
+                "field(s) 'feild1', 'field3' not in table, available columns are: field1, field2"
+                    .to_string()
+            ),
+            file.add_index(&["feild1", "field2", "field3"])
         );
     }
 
@@ -342,7 +2225,7 @@ mod tests {
 
         assert_eq!(
             Err("no rows found".to_string()),
-            file.find_table_row(&[condition], None)
+            file.find_table_row(&[condition], None, None)
         );
     }
 
@@ -365,7 +2248,7 @@ mod tests {
 
         assert_eq!(
             Err("no rows found".to_string()),
-            file.find_table_row(&[condition], Some(handle))
+            file.find_table_row(&[condition], None, Some(handle))
         );
     }
 }
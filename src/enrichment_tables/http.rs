@@ -0,0 +1,508 @@
+//! An enrichment table fetched from an HTTP endpoint serving a JSON array of objects, which is
+//! fetched again periodically to pick up changes.
+//!
+//! Each object is a row and the columns are the keys found in any of the objects. Keys missing
+//! from an object, or holding null, are absent cells, which are left out of the rows found and
+//! match no condition. Other values that aren't strings are held as their JSON. If fetching fails
+//! the table keeps serving the rows it last fetched.
+use super::file::File;
+use super::schema::SchemaConfig;
+use super::util::{emit_index_bytes, Indexes};
+use crate::config::{
+    EnrichmentTableConfig, EnrichmentTableDescription, GenerateConfig, ProxyConfig,
+};
+use crate::http::{Auth, HttpClient};
+use crate::internal_events::{EnrichmentTableLoaded, EnrichmentTableReloadFailed};
+use crate::tls::{TlsOptions, TlsSettings};
+use arc_swap::ArcSwap;
+use hyper::{body::HttpBody, Body, Request};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use vector_core::enrichment::{Condition, Explain, IndexHandle, IndexKind, Table};
+use vrl::Value;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct HttpConfig {
+    url: String,
+    /// How often the table is fetched again.
+    #[serde(default = "default_refresh_interval_secs")]
+    refresh_interval_secs: u64,
+    auth: Option<Auth>,
+    /// Additional headers sent with each request.
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// Responses larger than this fail to load.
+    #[serde(default = "default_max_response_bytes")]
+    max_response_bytes: usize,
+    tls: Option<TlsOptions>,
+    #[serde(default)]
+    schema: SchemaConfig,
+}
+
+const fn default_refresh_interval_secs() -> u64 {
+    300
+}
+
+const fn default_max_response_bytes() -> usize {
+    100 * 1024 * 1024
+}
+
+inventory::submit! {
+    EnrichmentTableDescription::new::<HttpConfig>("http")
+}
+
+impl GenerateConfig for HttpConfig {
+    fn generate_config() -> toml::Value {
+        toml::Value::try_from(Self {
+            url: "http://localhost:8080/table.json".to_string(),
+            refresh_interval_secs: default_refresh_interval_secs(),
+            auth: None,
+            headers: BTreeMap::new(),
+            max_response_bytes: default_max_response_bytes(),
+            tls: None,
+            schema: SchemaConfig::default(),
+        })
+        .unwrap()
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "http")]
+impl EnrichmentTableConfig for HttpConfig {
+    async fn build(
+        &self,
+        name: &str,
+        globals: &crate::config::GlobalOptions,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        let table = self.build_table(name, &globals.proxy).await?;
+        table.spawn_refresh(Duration::from_secs(self.refresh_interval_secs));
+        Ok(Box::new(table))
+    }
+}
+
+impl HttpConfig {
+    /// Creates the table, failing if the first fetch fails.
+    async fn build_table(&self, name: &str, proxy: &ProxyConfig) -> crate::Result<Http> {
+        if self.refresh_interval_secs == 0 {
+            return Err("refresh_interval_secs must be greater than zero".into());
+        }
+
+        let tls = TlsSettings::from_options(&self.tls)?;
+        let client = HttpClient::new(tls, proxy)?;
+        let bytes = self.fetch(&client).await?;
+        let config = self.clone();
+        let file = tokio::task::spawn_blocking(move || config.load(&bytes)).await??;
+
+        Ok(Http {
+            name: name.into(),
+            config: Arc::new(self.clone()),
+            client,
+            file: Arc::new(ArcSwap::from_pointee(file)),
+            indexes: Indexes::default(),
+        })
+    }
+
+    /// Fetches the body of the response, which is left to `load` to parse since that blocks.
+    async fn fetch(&self, client: &HttpClient) -> crate::Result<Vec<u8>> {
+        let mut request = Request::get(&self.url);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let mut request = request.body(Body::empty())?;
+        if let Some(auth) = &self.auth {
+            auth.apply(&mut request);
+        }
+
+        let response = client.send(request).await?;
+        if !response.status().is_success() {
+            return Err(format!("unexpected HTTP status {}", response.status()).into());
+        }
+
+        // Read the body a chunk at a time so that an oversized response is never held in full.
+        let mut body = response.into_body();
+        let mut bytes = Vec::new();
+        while let Some(chunk) = body.data().await {
+            bytes.extend_from_slice(&chunk?);
+            if bytes.len() > self.max_response_bytes {
+                return Err(format!(
+                    "response is larger than max_response_bytes of {}",
+                    self.max_response_bytes
+                )
+                .into());
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Parses the fetched response into the table.
+    fn load(&self, bytes: &[u8]) -> crate::Result<File> {
+        let (data, headers, absent) = parse(bytes)?;
+        let schema = self.schema.build(&headers, &data)?;
+        Ok(File::new(data, headers)
+            .with_schema(schema)
+            .with_absent(absent))
+    }
+}
+
+/// The rows, headers and absent cells of a table, see `File::with_absent`.
+type Parsed = (Vec<Vec<String>>, Vec<String>, HashMap<usize, Vec<usize>>);
+
+/// Parses a JSON array of objects into the rows and headers of a table, and the columns of each
+/// row that the object doesn't have a value for.
+fn parse(bytes: &[u8]) -> Result<Parsed, String> {
+    let objects: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_slice(bytes)
+        .map_err(|error| format!("response is not a JSON array of objects: {}", error))?;
+
+    let headers = objects
+        .iter()
+        .flat_map(|object| object.keys().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let mut absent = HashMap::new();
+    let data = objects
+        .iter()
+        .enumerate()
+        .map(|(idx, object)| {
+            headers
+                .iter()
+                .enumerate()
+                .map(|(column, header)| match object.get(header) {
+                    None | Some(serde_json::Value::Null) => {
+                        absent.entry(idx).or_insert_with(Vec::new).push(column);
+                        String::new()
+                    }
+                    Some(serde_json::Value::String(string)) => string.clone(),
+                    Some(value) => value.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok((data, headers, absent))
+}
+
+#[derive(Clone)]
+pub struct Http {
+    /// The name of the table, used in place of the URL in events since that may hold credentials.
+    name: Arc<str>,
+    config: Arc<HttpConfig>,
+    client: HttpClient,
+    file: Arc<ArcSwap<File>>,
+    /// Built again on each fetched copy of the table.
+    indexes: Indexes,
+}
+
+impl Http {
+    /// Fetches the table again and swaps it in once the indexes have been built on it. Each
+    /// copy has the same indexes in the same order, so the handles are the same for all.
+    async fn refresh(&self) -> crate::Result<()> {
+        let bytes = self.config.fetch(&self.client).await?;
+
+        let table = self.clone();
+        tokio::task::spawn_blocking(move || -> crate::Result<()> {
+            let mut file = table.config.load(&bytes)?;
+            let (indexes, _) = table.indexes.build(&mut file)?;
+            table.file.store(Arc::new(file));
+            emit_index_bytes(
+                &table.name,
+                indexes.iter().map(|(fields, _)| fields),
+                &**table.file.load(),
+            );
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Spawns a task fetching the table at the interval, which stops once the table and all its
+    /// clones have been dropped.
+    fn spawn_refresh(&self, period: Duration) {
+        let name = Arc::clone(&self.name);
+        let file = Arc::downgrade(&self.file);
+        let indexes = self.indexes.clone();
+        let config = Arc::clone(&self.config);
+        let client = self.client.clone();
+
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+
+                let table = match file.upgrade() {
+                    Some(file) => Http {
+                        name: Arc::clone(&name),
+                        config: Arc::clone(&config),
+                        client: client.clone(),
+                        file,
+                        indexes: indexes.clone(),
+                    },
+                    None => break,
+                };
+
+                let start = Instant::now();
+                match table.refresh().await {
+                    Ok(()) => emit!(EnrichmentTableLoaded {
+                        name: &name,
+                        elapsed: start.elapsed(),
+                    }),
+                    Err(error) => emit!(EnrichmentTableReloadFailed {
+                        name: &name,
+                        error: error.to_string(),
+                    }),
+                }
+            }
+        });
+    }
+
+    /// Adds an index of the given kind to the current copy of the table, and records it to build
+    /// on the copies fetched later.
+    fn add_index_of_kind(&self, fields: &[&str], kind: IndexKind) -> Result<IndexHandle, String> {
+        let current = &self.file;
+        self.indexes.add(fields, kind, |_| {
+            // The table is shared with running transforms so we add the index to a copy of it.
+            let mut file = File::clone(&current.load());
+            let handle = kind.add_to(&mut file, fields)?;
+            current.store(Arc::new(file));
+            Ok(handle)
+        })
+    }
+}
+
+impl Table for Http {
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        self.file.load().find_table_row(condition, select, index)
+    }
+
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        self.file.load().explain(condition, index)
+    }
+
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(fields, IndexKind::Equals)
+    }
+
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(&[field], IndexKind::Operator)
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.file.load().columns()
+    }
+
+    fn index_bytes(&self) -> Vec<usize> {
+        self.file.load().index_bytes()
+    }
+
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        // The table can be swapped out at any time, so the rows can't borrow from it.
+        let file = self.file.load_full();
+        Box::new(file.iter_rows().collect::<Vec<_>>().into_iter())
+    }
+}
+
+impl std::fmt::Debug for Http {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Http {}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{next_addr, wait_for_tcp};
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Response, Server,
+    };
+    use shared::btreemap;
+    use std::net::SocketAddr;
+    use std::sync::Mutex;
+
+    #[test]
+    fn generate_config() {
+        crate::test_util::test_generate_config::<HttpConfig>();
+    }
+
+    /// The status and body the mock server responds with, and the authorization headers it has
+    /// received.
+    #[derive(Clone, Default)]
+    struct Mock {
+        response: Arc<Mutex<(u16, String)>>,
+        authorization: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Mock {
+        fn respond(&self, status: u16, body: &str) {
+            *self.response.lock().unwrap() = (status, body.to_string());
+        }
+    }
+
+    async fn serve(mock: Mock) -> SocketAddr {
+        let addr = next_addr();
+        let make_svc = make_service_fn(move |_| {
+            let mock = mock.clone();
+            async move {
+                Ok::<_, crate::Error>(service_fn(move |request: Request<Body>| {
+                    let mock = mock.clone();
+                    async move {
+                        if let Some(value) = request.headers().get("Authorization") {
+                            let value = value.to_str().unwrap().to_string();
+                            mock.authorization.lock().unwrap().push(value);
+                        }
+                        let (status, body) = mock.response.lock().unwrap().clone();
+                        Ok::<_, crate::Error>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::from(body))
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+
+        tokio::spawn(async move {
+            if let Err(error) = Server::bind(&addr).serve(make_svc).await {
+                error!(message = "Server error.", %error);
+            }
+        });
+        wait_for_tcp(addr).await;
+
+        addr
+    }
+
+    fn config(addr: SocketAddr) -> HttpConfig {
+        HttpConfig {
+            url: format!("http://{}/table.json", addr),
+            refresh_interval_secs: 3600,
+            auth: Some(Auth::Bearer {
+                token: "secret".to_string(),
+            }),
+            headers: BTreeMap::new(),
+            max_response_bytes: 1024,
+            tls: None,
+            schema: SchemaConfig::default(),
+        }
+    }
+
+    fn condition(id: &str) -> Vec<Condition<'static>> {
+        vec![Condition::Equals {
+            field: "id",
+            value: id.to_string(),
+        }]
+    }
+
+    #[tokio::test]
+    async fn loads_and_refreshes() {
+        let mock = Mock::default();
+        mock.respond(
+            200,
+            r#"[{"id": "1", "name": "one", "score": 5}, {"id": "2", "name": "two"}]"#,
+        );
+        let addr = serve(mock.clone()).await;
+
+        let mut table = config(addr)
+            .build_table("table", &ProxyConfig::default())
+            .await
+            .unwrap();
+        let handle = table.add_index(&["id"]).unwrap();
+
+        assert_eq!(
+            Ok(btreemap! { "id" => "1", "name" => "one", "score" => "5" }),
+            table.find_table_row(&condition("1"), None, Some(handle))
+        );
+        assert_eq!(
+            Ok(btreemap! { "id" => "2", "name" => "two" }),
+            table.find_table_row(&condition("2"), None, Some(handle))
+        );
+        assert_eq!(
+            vec!["Bearer secret".to_string()],
+            *mock.authorization.lock().unwrap()
+        );
+
+        // A failed refresh keeps serving the rows that were last fetched.
+        mock.respond(500, "");
+        assert_eq!(
+            "unexpected HTTP status 500 Internal Server Error",
+            table.refresh().await.unwrap_err().to_string()
+        );
+        mock.respond(200, "{}");
+        assert!(table.refresh().await.is_err());
+        assert_eq!(2, table.iter_rows().count());
+
+        mock.respond(200, r#"[{"id": "3", "name": "three"}]"#);
+        table.refresh().await.unwrap();
+        assert_eq!(
+            Ok(btreemap! { "id" => "3", "name" => "three" }),
+            table.find_table_row(&condition("3"), None, Some(handle))
+        );
+        assert_eq!(
+            Err("no rows found".to_string()),
+            table.find_table_row(&condition("1"), None, Some(handle))
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_missing_values_absent() {
+        let mock = Mock::default();
+        mock.respond(
+            200,
+            r#"[{"id": "1", "name": ""}, {"id": "2", "name": null}, {"id": "3"}]"#,
+        );
+        let addr = serve(mock).await;
+
+        let table = config(addr)
+            .build_table("table", &ProxyConfig::default())
+            .await
+            .unwrap();
+
+        // Only the empty string is empty, the null and missing names are absent.
+        assert_eq!(
+            Ok(btreemap! { "id" => "1", "name" => "" }),
+            table.find_table_row(&condition("1"), None, None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "id" => "2" }),
+            table.find_table_row(&condition("2"), None, None)
+        );
+        assert_eq!(
+            Ok(btreemap! { "id" => "1", "name" => "" }),
+            table.find_table_row(&[Condition::IsEmpty { field: "name" }], None, None)
+        );
+        assert_eq!(
+            Err("no rows found".to_string()),
+            table.find_table_row(&[Condition::IsPresent { field: "name" }], None, None)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_response() {
+        let mock = Mock::default();
+        mock.respond(200, &format!(r#"[{{"id": "{}"}}]"#, "x".repeat(2048)));
+        let addr = serve(mock).await;
+
+        assert_eq!(
+            "response is larger than max_response_bytes of 1024",
+            config(addr)
+                .build_table("table", &ProxyConfig::default())
+                .await
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Joins two enrichment tables, so that a single lookup can find a row in one table and use it to
+//! find a row in another, rather than chaining two lookups in VRL.
+//!
+//! The `left` and `right` tables are other tables of the config, referred to by name, so their
+//! data is only loaded once and reloading either is seen by the join. The conditions of a lookup
+//! search the `left` table. The value of `left_field` in the row found is then looked up in the
+//! `right_field` column of the `right` table, using an index on that column, and the two rows are
+//! combined.
+//!
+//! Lookups find no rows if either table has no matching row, or if the row found in the `left`
+//! table has no value for `left_field`. Lookups error if either table has more than one matching
+//! row.
+use super::schema::number_key;
+use crate::config::{EnrichmentTableConfig, EnrichmentTableDescription, GenerateConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use vector_core::enrichment::{Condition, Explain, IndexHandle, Table, TableRegistry, TableSearch};
+use vrl::enrichment::{TableSearch as _, TableSetup};
+use vrl::Value;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct JoinConfig {
+    /// The name of the table searched with the conditions of a lookup.
+    left: String,
+    /// The name of the table searched with the value of `left_field` in the row found in `left`.
+    right: String,
+    left_field: String,
+    right_field: String,
+}
+
+inventory::submit! {
+    EnrichmentTableDescription::new::<JoinConfig>("join")
+}
+
+impl GenerateConfig for JoinConfig {
+    fn generate_config() -> toml::Value {
+        toml::Value::try_from(Self {
+            left: "users".to_string(),
+            right: "teams".to_string(),
+            left_field: "team_id".to_string(),
+            right_field: "id".to_string(),
+        })
+        .unwrap()
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "join")]
+impl EnrichmentTableConfig for JoinConfig {
+    async fn build(
+        &self,
+        _name: &str,
+        _globals: &crate::config::GlobalOptions,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        Ok(Box::new(Join::new(
+            crate::topology::builder::ENRICHMENT_TABLES.clone(),
+            &self.left,
+            &self.right,
+            &self.left_field,
+            &self.right_field,
+        )))
+    }
+}
+
+#[derive(Clone)]
+pub struct Join {
+    registry: TableRegistry,
+    tables: TableSearch,
+    left: String,
+    right: String,
+    left_field: String,
+    right_field: String,
+    /// The index on `right_field` of the right table, added along with the first index.
+    right_index: Option<IndexHandle>,
+}
+
+impl Join {
+    /// Joins the tables registered as `left` and `right`. The tables don't need to have been
+    /// loaded yet, their columns are checked when an index is added.
+    pub fn new(
+        registry: TableRegistry,
+        left: &str,
+        right: &str,
+        left_field: &str,
+        right_field: &str,
+    ) -> Self {
+        Self {
+            tables: registry.search(),
+            registry,
+            left: left.to_string(),
+            right: right.to_string(),
+            left_field: left_field.to_string(),
+            right_field: right_field.to_string(),
+            right_index: None,
+        }
+    }
+
+    /// Checks the fields are columns of their tables, and that the tables don't share any columns
+    /// other than the joined fields, since the combined rows couldn't hold both.
+    fn validate(&self) -> Result<(), String> {
+        let left_columns = self.registry.columns(&self.left)?;
+        let right_columns = self.registry.columns(&self.right)?;
+        super::util::validate_fields(&left_columns, &[&self.left_field])?;
+        super::util::validate_fields(&right_columns, &[&self.right_field])?;
+
+        let shared = right_columns
+            .into_iter()
+            .filter(|column| *column != self.right_field && left_columns.contains(column))
+            .map(|column| format!("'{}'", column))
+            .collect::<Vec<_>>();
+        if shared.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("column(s) {} in both tables", shared.join(", ")))
+        }
+    }
+
+    /// Adds the index on the joined field to the right table, once the tables have been checked.
+    fn add_right_index(&mut self) -> Result<(), String> {
+        self.validate()?;
+
+        if self.right_index.is_none() {
+            let right_index = self
+                .registry
+                .add_index(&self.right, &[self.right_field.as_str()])?;
+            self.right_index = Some(right_index);
+        }
+        Ok(())
+    }
+
+    /// Finds the row of the right table for a row of the left table, and adds it to the row.
+    fn join(&self, mut row: BTreeMap<String, Value>) -> Result<BTreeMap<String, Value>, String> {
+        let key = row
+            .get(&self.left_field)
+            .and_then(join_key)
+            .ok_or_else(|| "no rows found".to_string())?;
+
+        let condition = [Condition::Equals {
+            field: &self.right_field,
+            value: key,
+        }];
+        row.extend(
+            self.tables
+                .find_table_row(&self.right, &condition, None, self.right_index)?,
+        );
+
+        Ok(row)
+    }
+}
+
+/// Converts a value of the left table to the value searched for in the right table. Numbers are
+/// written in one form, so a float holding a whole number finds the integer. Empty and null
+/// values, or values that can't be held in a cell, aren't searched for.
+fn join_key(value: &Value) -> Option<String> {
+    match value {
+        Value::Bytes(bytes) if bytes.is_empty() => None,
+        Value::Bytes(bytes) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        Value::Integer(_) | Value::Float(_) => number_key(&value.to_string()),
+        Value::Boolean(_) => Some(value.to_string()),
+        Value::Timestamp(timestamp) => Some(timestamp.to_rfc3339()),
+        _ => None,
+    }
+}
+
+impl Table for Join {
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        if let Some(select) = select {
+            super::util::validate_fields(&self.columns(), select)?;
+        }
+
+        let left = self
+            .tables
+            .find_table_row(&self.left, condition, None, index)?;
+        let mut row = self.join(left)?;
+        if let Some(select) = select {
+            row.retain(|column, _| select.contains(column));
+        }
+
+        Ok(row)
+    }
+
+    /// Explains the search of the left table, the right table is always searched with its index
+    /// on the joined field.
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        self.tables
+            .explain(&self.left, condition, index)
+            .ok()
+            .flatten()
+    }
+
+    /// Adds an index to the left table, since the conditions of a lookup only search that table,
+    /// and the index on the joined field to the right table.
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        self.add_right_index()?;
+        self.registry.add_index(&self.left, fields)
+    }
+
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        self.add_right_index()?;
+        self.registry.add_operator_index(&self.left, field)
+    }
+
+    fn columns(&self) -> Vec<String> {
+        let mut columns = self.registry.columns(&self.left).unwrap_or_default();
+        // The only column in both tables is the joined field, if they have the same name.
+        let right = self
+            .registry
+            .columns(&self.right)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|column| !columns.contains(column))
+            .collect::<Vec<_>>();
+        columns.extend(right);
+        columns
+    }
+
+    /// Iterates over the rows of the left table that have a row in the right table.
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        Box::new(
+            self.tables
+                .rows(&self.left)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(move |row| self.join(row).ok()),
+        )
+    }
+}
+
+impl std::fmt::Debug for Join {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Join {} {} = {} {}",
+            self.left, self.left_field, self.right, self.right_field
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrichment_tables::file::File;
+    use crate::enrichment_tables::schema::{ColumnType, SchemaConfig};
+    use shared::btreemap;
+    use std::collections::HashMap;
+    use vrl::enrichment::TableSearch as _;
+
+    #[test]
+    fn generate_config() {
+        crate::test_util::test_generate_config::<JoinConfig>();
+    }
+
+    fn table(headers: &[&str], rows: &[&[&str]]) -> File {
+        File::new(
+            rows.iter()
+                .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+                .collect(),
+            headers.iter().map(|header| header.to_string()).collect(),
+        )
+    }
+
+    /// Registers the tables and a join of the users and teams tables, returning the registry and
+    /// the join's handle for an index on `user`.
+    fn registry(
+        users: File,
+        teams: File,
+        left_field: &str,
+        right_field: &str,
+    ) -> (TableRegistry, Result<IndexHandle, String>) {
+        let mut registry = TableRegistry::default();
+        let join = Join::new(registry.clone(), "users", "teams", left_field, right_field);
+
+        let mut tables: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        tables.insert("users".to_string(), Box::new(users));
+        tables.insert("teams".to_string(), Box::new(teams));
+        tables.insert("join".to_string(), Box::new(join));
+        registry.load(tables);
+
+        let handle = registry.add_index("join", &["user"]);
+        registry.finish_load();
+        (registry, handle)
+    }
+
+    fn users() -> File {
+        table(
+            &["user", "team_id"],
+            &[
+                &["alice", "1"],
+                &["bob", ""],
+                &["carol", "3"],
+                &["dave", "4"],
+            ],
+        )
+    }
+
+    fn teams() -> File {
+        table(
+            &["id", "team", "owner"],
+            &[
+                &["1", "platform", "erin"],
+                &["4", "data", "frank"],
+                &["4", "data", "grace"],
+            ],
+        )
+    }
+
+    fn condition(user: &str) -> Vec<Condition<'_>> {
+        vec![Condition::Equals {
+            field: "user",
+            value: user.to_string(),
+        }]
+    }
+
+    #[test]
+    fn joins_tables() {
+        let (registry, handle) = registry(users(), teams(), "team_id", "id");
+        let handle = handle.unwrap();
+        let tables = registry.search();
+
+        for index in [None, Some(handle)].iter().copied() {
+            assert_eq!(
+                Ok(btreemap! {
+                    "user" => "alice",
+                    "team_id" => "1",
+                    "id" => "1",
+                    "team" => "platform",
+                    "owner" => "erin",
+                }),
+                tables.find_table_row("join", &condition("alice"), None, index)
+            );
+
+            let select = vec!["user".to_string(), "team".to_string()];
+            assert_eq!(
+                Ok(btreemap! { "user" => "alice", "team" => "platform" }),
+                tables.find_table_row("join", &condition("alice"), Some(&select), index)
+            );
+        }
+
+        // The indexes are added to the joined tables.
+        let indexes = registry
+            .index_bytes()
+            .into_iter()
+            .map(|(name, indexes)| {
+                let fields = indexes
+                    .into_iter()
+                    .map(|(fields, _)| fields)
+                    .collect::<Vec<_>>();
+                (name, fields)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("join".to_string(), vec![]),
+                ("teams".to_string(), vec![vec!["id".to_string()]]),
+                ("users".to_string(), vec![vec!["user".to_string()]]),
+            ],
+            indexes
+        );
+        assert_eq!(1, tables.rows("join").unwrap().len());
+    }
+
+    #[test]
+    fn missing_and_ambiguous_keys() {
+        let (registry, _) = registry(users(), teams(), "team_id", "id");
+        let tables = registry.search();
+
+        // Bob has no team, and Carol's team isn't in the teams table.
+        assert_eq!(
+            Err("no rows found".to_string()),
+            tables.find_table_row("join", &condition("bob"), None, None)
+        );
+        assert_eq!(
+            Err("no rows found".to_string()),
+            tables.find_table_row("join", &condition("carol"), None, None)
+        );
+        // Dave's team is in the teams table twice.
+        assert_eq!(
+            Err("2 rows found".to_string()),
+            tables.find_table_row("join", &condition("dave"), None, None)
+        );
+    }
+
+    #[test]
+    fn joins_numbers_by_value() {
+        let float = SchemaConfig {
+            types: vec![("team_id".to_string(), ColumnType::Float)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        }
+        .build(&["user".to_string(), "team_id".to_string()], &[])
+        .unwrap();
+        let users = users().with_schema(float);
+        let teams = table(&["id", "team"], &[&["1", "platform"]]);
+
+        // The float is searched for as the integer held by the untyped column.
+        let (registry, _) = registry(users, teams, "team_id", "id");
+        assert_eq!(
+            Ok(btreemap! {
+                "user" => "alice",
+                "team_id" => 1.0,
+                "id" => "1",
+                "team" => "platform",
+            }),
+            registry
+                .search()
+                .find_table_row("join", &condition("alice"), None, None)
+        );
+    }
+
+    #[test]
+    fn rejects_shared_columns() {
+        let users = table(&["user", "id"], &[]);
+        let teams = table(&["id", "user"], &[]);
+
+        assert_eq!(
+            Err("column(s) 'user' in both tables".to_string()),
+            registry(users, teams, "id", "id").1
+        );
+    }
+}
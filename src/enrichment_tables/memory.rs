@@ -0,0 +1,482 @@
+//! An enrichment table held in memory that rows can be written to whilst it is serving lookups,
+//! for tables built up at runtime such as a denylist populated by a `remap` transform calling
+//! `upsert_enrichment_table_record`.
+//!
+//! Rows are identified by the values of their key columns, normalized as lookups are. Writes add
+//! the row to the existing indexes rather than rebuilding them, and hold a write lock whilst doing
+//! so, so a lookup sees the table either before or after a write and never part way through one.
+//! The table starts empty each time it is built, so the rows are lost when Vector restarts or the
+//! table's config is changed.
+use super::{file::File, normalize::Normalize, util::emit_index_bytes};
+use crate::config::{EnrichmentTableConfig, EnrichmentTableDescription, GenerateConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use vector_core::enrichment::{Condition, Explain, IndexHandle, IndexKind, Table};
+use vrl::Value;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct MemoryConfig {
+    /// The columns of the table.
+    columns: Vec<String>,
+    /// The columns identifying a row. Writing a row with the same key replaces it.
+    key: Vec<String>,
+    #[serde(default)]
+    normalize: Normalize,
+}
+
+impl GenerateConfig for MemoryConfig {
+    fn generate_config() -> toml::Value {
+        toml::Value::try_from(Self {
+            columns: vec!["ip".to_string(), "reason".to_string()],
+            key: vec!["ip".to_string()],
+            normalize: Normalize::default(),
+        })
+        .unwrap()
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde(name = "memory")]
+impl EnrichmentTableConfig for MemoryConfig {
+    async fn build(
+        &self,
+        name: &str,
+        _globals: &crate::config::GlobalOptions,
+    ) -> crate::Result<Box<dyn Table + Send + Sync>> {
+        let key = self.key.iter().map(String::as_str).collect::<Vec<_>>();
+        let table = Memory::new(name, self.columns.clone(), &key, self.normalize.clone())?;
+        table.spawn_index_bytes(INDEX_BYTES_INTERVAL);
+
+        Ok(Box::new(table))
+    }
+}
+
+inventory::submit! {
+    EnrichmentTableDescription::new::<MemoryConfig>("memory")
+}
+
+/// How often the memory used by the indexes is emitted whilst rows are being written. Measuring
+/// the indexes takes time in proportion to the rows, so it isn't done on every write.
+const INDEX_BYTES_INTERVAL: Duration = Duration::from_secs(10);
+
+struct State {
+    file: File,
+    /// The position of each row, by the normalized values of its key columns.
+    keys: HashMap<Vec<String>, usize>,
+    /// The fields of each index, in the order they were added.
+    indexes: Vec<Vec<String>>,
+    /// Whether the indexes have changed since their memory was last emitted.
+    changed: AtomicBool,
+}
+
+impl State {
+    /// Emits the memory used by the indexes if they have changed since it was last emitted.
+    fn emit_index_bytes(&self, name: &str) {
+        if self.changed.swap(false, Ordering::Relaxed) {
+            emit_index_bytes(name, &self.indexes, &self.file);
+        }
+    }
+}
+
+/// Clones share the rows, so writes through any clone are seen by lookups on all of them.
+#[derive(Clone)]
+pub struct Memory {
+    name: String,
+    headers: Vec<String>,
+    key: Vec<usize>,
+    normalize: Normalize,
+    state: Arc<RwLock<State>>,
+}
+
+impl Memory {
+    /// Creates an empty table with the given columns, where rows are identified by the values of
+    /// the `key` columns. The key, and the cells compared by equality conditions, are normalized
+    /// by `normalize`. The `name` identifies the table in internal events.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the key is empty or names a column not in the table.
+    pub fn new(
+        name: impl Into<String>,
+        headers: Vec<String>,
+        key: &[&str],
+        normalize: Normalize,
+    ) -> Result<Self, String> {
+        if key.is_empty() {
+            return Err("no key columns given".to_string());
+        }
+        super::util::validate_fields(&headers, key)?;
+
+        let key = key
+            .iter()
+            .filter_map(|field| headers.iter().position(|header| header == field))
+            .collect();
+
+        Ok(Self {
+            name: name.into(),
+            key,
+            state: Arc::new(RwLock::new(State {
+                file: File::new(Vec::new(), headers.clone()).with_normalize(normalize.clone()),
+                keys: HashMap::new(),
+                indexes: Vec::new(),
+                changed: AtomicBool::new(false),
+            })),
+            headers,
+            normalize,
+        })
+    }
+
+    /// Inserts the row, erroring rather than replacing a row with the same key.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the row has a column not in the table, or a value that can't be held in a cell,
+    /// or a row with the same key exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    pub fn insert_row(&self, row: BTreeMap<String, Value>) -> Result<(), String> {
+        self.write_row(row, false)
+    }
+
+    fn write_row(&self, mut row: BTreeMap<String, Value>, replace: bool) -> Result<(), String> {
+        let fields = row.keys().collect::<Vec<_>>();
+        super::util::validate_fields(&self.headers, &fields)?;
+
+        // Columns missing from the row, or holding null, are empty cells.
+        let row = self
+            .headers
+            .iter()
+            .map(|column| match row.remove(column) {
+                None | Some(Value::Null) => Ok(String::new()),
+                Some(Value::Bytes(value)) => Ok(String::from_utf8_lossy(&value).into_owned()),
+                Some(Value::Integer(value)) => Ok(value.to_string()),
+                Some(Value::Float(value)) => Ok(value.to_string()),
+                Some(Value::Boolean(value)) => Ok(value.to_string()),
+                Some(Value::Timestamp(value)) => Ok(value.to_rfc3339()),
+                Some(value) => Err(format!(
+                    "column '{}' can't hold a value of kind {}",
+                    column,
+                    value.kind()
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let key = self
+            .key
+            .iter()
+            .map(|idx| self.normalize.key(&row[*idx]))
+            .collect::<Vec<_>>();
+
+        let mut state = self.state.write().unwrap();
+        match state.keys.get(&key).copied() {
+            Some(idx) if replace => state.file.replace_row(idx, row),
+            Some(_) => return Err(format!("row with key '{}' exists", key.join("', '"))),
+            None => {
+                let idx = state.file.push_row(row);
+                state.keys.insert(key, idx);
+            }
+        }
+        *state.changed.get_mut() = true;
+
+        Ok(())
+    }
+
+    /// Spawns a task emitting the memory used by the indexes at the interval, whenever they have
+    /// changed, which stops once the table and all its clones have been dropped.
+    fn spawn_index_bytes(&self, period: Duration) {
+        let name = self.name.clone();
+        let state = Arc::downgrade(&self.state);
+
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+
+                match state.upgrade() {
+                    Some(state) => state.read().unwrap().emit_index_bytes(&name),
+                    None => break,
+                }
+            }
+        });
+    }
+
+    /// Adds an index of the given kind, which is kept up to date as rows are written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    fn add_index_of_kind(&self, fields: &[&str], kind: IndexKind) -> Result<IndexHandle, String> {
+        let mut state = self.state.write().unwrap();
+        let handle = kind.add_to(&mut state.file, fields)?;
+        state
+            .indexes
+            .push(fields.iter().map(|field| (*field).to_string()).collect());
+        *state.changed.get_mut() = true;
+
+        Ok(handle)
+    }
+}
+
+impl Table for Memory {
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        self.state
+            .read()
+            .unwrap()
+            .file
+            .find_table_row(condition, select, index)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        self.state.read().unwrap().file.explain(condition, index)
+    }
+
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(fields, IndexKind::Equals)
+    }
+
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(&[field], IndexKind::Operator)
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.headers.clone()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    fn upsert_row(&self, row: BTreeMap<String, Value>) -> Result<(), String> {
+        self.write_row(row, true)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    fn index_bytes(&self) -> Vec<usize> {
+        self.state.read().unwrap().file.index_bytes()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the RwLock is poisoned.
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        // The rows can be written to at any time, so they can't borrow from the table.
+        let rows = self
+            .state
+            .read()
+            .unwrap()
+            .file
+            .iter_rows()
+            .collect::<Vec<_>>();
+        Box::new(rows.into_iter())
+    }
+}
+
+impl std::fmt::Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.state.read() {
+            Ok(state) => write!(f, "Memory {} row(s)", state.keys.len()),
+            Err(_) => write!(f, "Memory poisoned"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrichment_tables::normalize::Normalizer;
+    use crate::event::MetricValue;
+    use crate::metrics::{capture_metrics, get_controller};
+    use shared::btreemap;
+    use std::thread;
+
+    fn memory() -> Memory {
+        Memory::new(
+            "memory",
+            vec!["ip".to_string(), "reason".to_string(), "source".to_string()],
+            &["ip"],
+            Normalize::new(vec![Normalizer::Trim]),
+        )
+        .unwrap()
+    }
+
+    fn row(ip: &str, reason: &str, source: &str) -> BTreeMap<String, Value> {
+        btreemap! { "ip" => ip, "reason" => reason, "source" => source }
+    }
+
+    fn condition<'a>(field: &'a str, value: &str) -> Vec<Condition<'a>> {
+        vec![Condition::Equals {
+            field,
+            value: value.to_string(),
+        }]
+    }
+
+    #[test]
+    fn upserted_rows_are_visible() {
+        let mut table = memory();
+        let ip = table.add_index(&["ip"]).unwrap();
+        let reason = table.add_index(&["reason", "source"]).unwrap();
+
+        table.insert_row(row("10.0.0.1", "scan", "ids")).unwrap();
+        assert_eq!(
+            Ok(btreemap! { "ip" => "10.0.0.1", "reason" => "scan", "source" => "ids" }),
+            table.find_table_row(&condition("ip", "10.0.0.1"), None, Some(ip))
+        );
+
+        // Inserting rejects an existing key, upserting replaces the row.
+        assert_eq!(
+            Err("row with key '10.0.0.1' exists".to_string()),
+            table.insert_row(row("10.0.0.1", "spam", "mail"))
+        );
+        table.upsert_row(row("10.0.0.1", "spam", "mail")).unwrap();
+        table.upsert_row(row("10.0.0.2", "scan", "ids")).unwrap();
+
+        for index in [None, Some(ip), Some(reason)].iter().copied() {
+            assert_eq!(
+                Ok(btreemap! { "ip" => "10.0.0.1", "reason" => "spam", "source" => "mail" }),
+                table.find_table_row(&condition("ip", "10.0.0.1"), None, index)
+            );
+            // The replaced row has been removed from the indexes.
+            assert_eq!(
+                Ok(btreemap! { "ip" => "10.0.0.2", "reason" => "scan", "source" => "ids" }),
+                table.find_table_row(&condition("reason", "scan"), None, index)
+            );
+        }
+        assert_eq!(2, table.iter_rows().count());
+    }
+
+    #[test]
+    fn keys_are_normalized() {
+        let table = memory();
+        table.insert_row(row("10.0.0.1", "scan", "ids")).unwrap();
+
+        assert_eq!(
+            Err("row with key '10.0.0.1' exists".to_string()),
+            table.insert_row(row(" 10.0.0.1 ", "spam", "mail"))
+        );
+        table.upsert_row(row(" 10.0.0.1 ", "spam", "mail")).unwrap();
+        assert_eq!(1, table.iter_rows().count());
+    }
+
+    #[test]
+    fn converts_values_to_cells() {
+        let table = memory();
+        table
+            .upsert_row(btreemap! { "ip" => "10.0.0.1", "reason" => 3, "source" => Value::Null })
+            .unwrap();
+        assert_eq!(
+            Ok(btreemap! { "ip" => "10.0.0.1", "reason" => "3", "source" => "" }),
+            table.find_table_row(&condition("ip", "10.0.0.1"), None, None)
+        );
+
+        assert_eq!(
+            Err(
+                "field(s) 'port' not in table, available columns are: ip, reason, source"
+                    .to_string()
+            ),
+            table.upsert_row(btreemap! { "ip" => "10.0.0.2", "port" => 22 })
+        );
+        assert_eq!(
+            Err(r#"column 'reason' can't hold a value of kind "array""#.to_string()),
+            table.upsert_row(btreemap! { "ip" => "10.0.0.2", "reason" => vec!["scan"] })
+        );
+    }
+
+    #[test]
+    fn readers_never_see_partial_rows() {
+        let mut table = memory();
+        let handle = table.add_index(&["ip"]).unwrap();
+        table.insert_row(row("10.0.0.1", "0", "0")).unwrap();
+
+        let readers = (0..4)
+            .map(|_| {
+                let table = table.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        let found = table
+                            .find_table_row(&condition("ip", "10.0.0.1"), None, Some(handle))
+                            .unwrap();
+                        // Every write sets both columns to the same value.
+                        assert_eq!(found["reason"], found["source"]);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for count in 1..1000 {
+            let count = count.to_string();
+            table.upsert_row(row("10.0.0.1", &count, &count)).unwrap();
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn emits_index_bytes_after_writes() {
+        let _ = crate::metrics::init();
+        let table_bytes = || {
+            let controller = get_controller().expect("no controller");
+            capture_metrics(controller).find_map(|metric| {
+                let tags = metric.tags()?;
+                if metric.name() != "enrichment_table_index_bytes"
+                    || tags.get("table").map(String::as_str) != Some("memory_index_bytes_test")
+                {
+                    return None;
+                }
+                match metric.value() {
+                    MetricValue::Gauge { value } => Some(*value),
+                    _ => None,
+                }
+            })
+        };
+
+        let mut table = Memory::new(
+            "memory_index_bytes_test",
+            vec!["ip".to_string(), "reason".to_string()],
+            &["ip"],
+            Normalize::default(),
+        )
+        .unwrap();
+        table.add_index(&["ip"]).unwrap();
+
+        let emit = || table.state.read().unwrap().emit_index_bytes(&table.name);
+
+        table.upsert_row(btreemap! { "ip" => "10.0.0.1" }).unwrap();
+        emit();
+        let small = table_bytes().unwrap();
+        for ip in 2..100 {
+            table
+                .upsert_row(btreemap! { "ip" => format!("10.0.0.{}", ip) })
+                .unwrap();
+        }
+        emit();
+        assert!(table_bytes().unwrap() > small);
+
+        // Nothing is emitted until the indexes change again.
+        assert!(!table.state.read().unwrap().changed.load(Ordering::Relaxed));
+    }
+}
@@ -1,4 +1,24 @@
 pub use vector_core::enrichment::{Condition, IndexHandle, Table};
 
+#[cfg(feature = "enrichment-tables-file")]
+pub mod background;
+#[cfg(feature = "enrichment-tables-file")]
+pub mod cache;
+#[cfg(feature = "enrichment-tables-cidr")]
+pub mod cidr;
 #[cfg(feature = "enrichment-tables-file")]
 pub mod file;
+#[cfg(feature = "enrichment-tables-http")]
+pub mod http;
+#[cfg(feature = "enrichment-tables-join")]
+pub mod join;
+#[cfg(feature = "enrichment-tables-memory")]
+pub mod memory;
+#[cfg(feature = "enrichment-tables-file")]
+pub mod normalize;
+#[cfg(feature = "enrichment-tables-file")]
+pub mod partitioned;
+#[cfg(feature = "enrichment-tables-file")]
+pub mod schema;
+#[cfg(feature = "enrichment-tables-file")]
+mod util;
@@ -0,0 +1,71 @@
+//! Normalizes the values compared by equality conditions, so that lookups from messy event data
+//! still match clean reference data. The same steps are applied to the cells when they are
+//! indexed and to the values of lookups, so the two always agree.
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalizer {
+    /// Removes leading and trailing whitespace.
+    Trim,
+    /// Composes characters into Unicode Normalization Form C, so that an accented letter written
+    /// as a letter followed by a combining accent equals the single character.
+    Nfc,
+    /// Removes accents and other combining marks, so that `é` equals `e`.
+    StripAccents,
+}
+
+impl Normalizer {
+    fn apply(self, value: String) -> String {
+        match self {
+            Self::Trim => value.trim().to_string(),
+            Self::Nfc => value.nfc().collect(),
+            Self::StripAccents => value
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .nfc()
+                .collect(),
+        }
+    }
+}
+
+/// The normalizers applied in order, after lowercasing since lookups always ignore case.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct Normalize(Vec<Normalizer>);
+
+impl Normalize {
+    pub fn new(normalizers: Vec<Normalizer>) -> Self {
+        Self(normalizers)
+    }
+
+    /// Returns the value that is compared in place of the cell or lookup value.
+    pub fn key(&self, value: &str) -> String {
+        self.0
+            .iter()
+            .fold(value.to_lowercase(), |value, normalizer| {
+                normalizer.apply(value)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_normalizers_in_order() {
+        let decomposed = " Ze\u{301}bre ";
+
+        assert_eq!(" ze\u{301}bre ", Normalize::default().key(decomposed));
+        assert_eq!(
+            "z\u{e9}bre",
+            Normalize::new(vec![Normalizer::Trim, Normalizer::Nfc]).key(decomposed)
+        );
+        assert_eq!(
+            "zebre",
+            Normalize::new(vec![Normalizer::Trim, Normalizer::StripAccents]).key(" Z\u{e9}bre ")
+        );
+    }
+}
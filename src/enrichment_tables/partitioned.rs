@@ -0,0 +1,286 @@
+//! Splits a table into partitions by the value of one column, such as a tenant, so that each
+//! partition holds its own smaller indexes. Every lookup must give the partition with an
+//! equality condition on that column, and only searches the rows of that partition.
+//!
+//! The partitions are a table of their own, rather than being kept by the `TableRegistry`, so
+//! that the registry keeps managing a single table per name. Transforms are given one
+//! `IndexHandle` for the whole table, which is mapped here to the handle of each partition, and
+//! reloading or replacing the table swaps every partition at once.
+use super::{normalize::Normalize, util::inner_handle};
+use std::collections::{BTreeMap, HashMap};
+use vector_core::enrichment::{Condition, Explain, IndexHandle, IndexKind, Table};
+use vrl::Value;
+
+#[derive(Clone)]
+struct Partition {
+    table: Box<dyn Table + Send + Sync>,
+    /// Maps the handles we have returned from `add_index` to the handles of this partition.
+    handles: Vec<IndexHandle>,
+}
+
+#[derive(Clone)]
+pub struct Partitioned {
+    field: String,
+    columns: Vec<String>,
+    /// Applied to the values of the partition column, as it is to those of equality conditions.
+    normalize: Normalize,
+    /// The partitions keyed by the normalized value of the partition column.
+    partitions: HashMap<String, Partition>,
+    indexes: usize,
+}
+
+impl Partitioned {
+    /// Creates a table from the partitions, keyed by their value of the `field` column, which is
+    /// compared after applying `normalize`. The `columns` are those of every partition.
+    pub fn new(
+        field: impl Into<String>,
+        columns: Vec<String>,
+        normalize: Normalize,
+        partitions: HashMap<String, Box<dyn Table + Send + Sync>>,
+    ) -> Self {
+        Self {
+            field: field.into(),
+            columns,
+            partitions: partitions
+                .into_iter()
+                .map(|(key, table)| {
+                    (
+                        normalize.key(&key),
+                        Partition {
+                            table,
+                            handles: Vec::new(),
+                        },
+                    )
+                })
+                .collect(),
+            normalize,
+            indexes: 0,
+        }
+    }
+
+    /// Finds the partition that the condition searches.
+    fn partition(&self, condition: &[Condition]) -> Result<Option<&Partition>, String> {
+        condition
+            .iter()
+            .find_map(|condition| match condition {
+                Condition::Equals { field, value } if *field == self.field => {
+                    Some(self.partitions.get(&self.normalize.key(value)))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| format!("no value given for partition field '{}'", self.field))
+    }
+
+    /// Adds an index of the given kind to every partition.
+    fn add_index_of_kind(
+        &mut self,
+        fields: &[&str],
+        kind: IndexKind,
+    ) -> Result<IndexHandle, String> {
+        super::util::validate_fields(&self.columns, fields)?;
+
+        for partition in self.partitions.values_mut() {
+            let handle = kind.add_to(&mut *partition.table, fields)?;
+            partition.handles.push(handle);
+        }
+
+        self.indexes += 1;
+        Ok(IndexHandle(self.indexes - 1))
+    }
+}
+
+impl Table for Partitioned {
+    fn find_table_row<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        select: Option<&[String]>,
+        index: Option<IndexHandle>,
+    ) -> Result<BTreeMap<String, Value>, String> {
+        match self.partition(condition)? {
+            Some(partition) => {
+                let index = inner_handle(&partition.handles, index);
+                partition.table.find_table_row(condition, select, index)
+            }
+            None => Err("no rows found".to_string()),
+        }
+    }
+
+    /// Explains the lookup in the partition it is routed to, if any.
+    fn explain<'a>(
+        &self,
+        condition: &'a [Condition<'a>],
+        index: Option<IndexHandle>,
+    ) -> Option<Explain> {
+        let partition = self.partition(condition).ok().flatten()?;
+        let index = inner_handle(&partition.handles, index);
+        partition.table.explain(condition, index)
+    }
+
+    fn add_index(&mut self, fields: &[&str]) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(fields, IndexKind::Equals)
+    }
+
+    fn add_operator_index(&mut self, field: &str) -> Result<IndexHandle, String> {
+        self.add_index_of_kind(&[field], IndexKind::Operator)
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.columns.clone()
+    }
+
+    /// Returns the memory used by each index summed over the partitions.
+    fn index_bytes(&self) -> Vec<usize> {
+        let mut bytes = vec![0; self.indexes];
+        for partition in self.partitions.values() {
+            let partition_bytes = partition.table.index_bytes();
+            for (total, IndexHandle(handle)) in bytes.iter_mut().zip(&partition.handles) {
+                *total += partition_bytes.get(*handle).copied().unwrap_or_default();
+            }
+        }
+
+        bytes
+    }
+
+    fn iter_rows(&self) -> Box<dyn Iterator<Item = BTreeMap<String, Value>> + '_> {
+        Box::new(
+            self.partitions
+                .values()
+                .flat_map(|partition| partition.table.iter_rows()),
+        )
+    }
+}
+
+impl std::fmt::Debug for Partitioned {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Partitioned by {} into {} partitions",
+            self.field,
+            self.partitions.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrichment_tables::{file::File, normalize::Normalizer};
+    use shared::btreemap;
+
+    fn partitioned() -> Partitioned {
+        let columns = vec![
+            "tenant".to_string(),
+            "host".to_string(),
+            "owner".to_string(),
+        ];
+        let partition = |rows: Vec<[&str; 3]>| -> Box<dyn Table + Send + Sync> {
+            Box::new(File::new(
+                rows.into_iter()
+                    .map(|row| row.iter().map(|col| col.to_string()).collect())
+                    .collect(),
+                columns.clone(),
+            ))
+        };
+
+        let mut partitions = HashMap::new();
+        partitions.insert(
+            "acme".to_string(),
+            partition(vec![["acme", "db", "alice"], ["acme", "web", "bob"]]),
+        );
+        partitions.insert(
+            "Initech".to_string(),
+            partition(vec![["initech", "db", "carol"]]),
+        );
+
+        Partitioned::new("tenant", columns.clone(), Normalize::default(), partitions)
+    }
+
+    fn condition<'a>(tenant: &str, host: &str) -> Vec<Condition<'a>> {
+        vec![
+            Condition::Equals {
+                field: "tenant",
+                value: tenant.to_string(),
+            },
+            Condition::Equals {
+                field: "host",
+                value: host.to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn routes_to_partition() {
+        let mut table = partitioned();
+        let handle = table.add_index(&["tenant", "host"]).unwrap();
+
+        for index in [None, Some(handle)].iter().copied() {
+            assert_eq!(
+                Ok(btreemap! { "tenant" => "acme", "host" => "db", "owner" => "alice" }),
+                table.find_table_row(&condition("acme", "db"), None, index)
+            );
+            assert_eq!(
+                Ok(btreemap! { "tenant" => "initech", "host" => "db", "owner" => "carol" }),
+                table.find_table_row(&condition("INITECH", "db"), None, index)
+            );
+        }
+    }
+
+    #[test]
+    fn isolates_partitions() {
+        let table = partitioned();
+
+        // The host is in the acme partition, but not the initech one.
+        assert_eq!(
+            Err("no rows found".to_string()),
+            table.find_table_row(&condition("initech", "web"), None, None)
+        );
+        assert_eq!(
+            Err("no rows found".to_string()),
+            table.find_table_row(&condition("globex", "db"), None, None)
+        );
+        assert_eq!(
+            Err("no value given for partition field 'tenant'".to_string()),
+            table.find_table_row(&condition("acme", "db")[1..], None, None)
+        );
+    }
+
+    #[test]
+    fn normalizes_partition_values() {
+        let columns = vec!["tenant".to_string(), "owner".to_string()];
+        let normalize = Normalize::new(vec![Normalizer::Trim]);
+        let mut partitions: HashMap<String, Box<dyn Table + Send + Sync>> = HashMap::new();
+        partitions.insert(
+            "Acme".to_string(),
+            Box::new(
+                File::new(
+                    vec![vec!["Acme".to_string(), "alice".to_string()]],
+                    columns.clone(),
+                )
+                .with_normalize(normalize.clone()),
+            ),
+        );
+        let table = Partitioned::new("tenant", columns, normalize, partitions);
+
+        let condition = Condition::Equals {
+            field: "tenant",
+            value: " ACME ".to_string(),
+        };
+        assert_eq!(
+            Ok(btreemap! { "tenant" => "Acme", "owner" => "alice" }),
+            table.find_table_row(&[condition], None, None)
+        );
+    }
+
+    #[test]
+    fn add_index_validates_fields() {
+        let mut table = partitioned();
+
+        assert_eq!(
+            Err(
+                "field(s) 'missing' not in table, available columns are: tenant, host, owner"
+                    .to_string()
+            ),
+            table.add_index(&["missing"])
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Types for the columns of enrichment tables, so that lookups return typed values rather than
+//! the strings read from the file.
+use super::normalize::Normalize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use vrl::Value;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    /// An RFC 3339 timestamp.
+    Timestamp,
+}
+
+impl ColumnType {
+    /// Parses the cell as this type, returning `None` if it isn't valid.
+    fn parse(self, cell: &str) -> Option<Value> {
+        match self {
+            Self::String => Some(cell.into()),
+            Self::Integer => cell.parse::<i64>().ok().map(Value::from),
+            Self::Float => cell
+                .parse::<f64>()
+                .ok()
+                .filter(|float| !float.is_nan())
+                .map(Value::from),
+            Self::Boolean => cell.parse::<bool>().ok().map(Value::from),
+            Self::Timestamp => DateTime::parse_from_rfc3339(cell)
+                .ok()
+                .map(|timestamp| Value::from(timestamp.with_timezone(&Utc))),
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String => write!(f, "string"),
+            Self::Integer => write!(f, "integer"),
+            Self::Float => write!(f, "float"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::Timestamp => write!(f, "timestamp"),
+        }
+    }
+}
+
+/// What to do with cells that can't be parsed as the type of their column.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvalidValues {
+    /// Fail to load the table.
+    Error,
+    /// Return the cell as a string.
+    KeepString,
+}
+
+impl Default for InvalidValues {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SchemaConfig {
+    /// The types of the columns, columns not given are strings.
+    #[serde(default)]
+    pub types: HashMap<String, ColumnType>,
+    #[serde(default)]
+    pub invalid_values: InvalidValues,
+}
+
+impl SchemaConfig {
+    /// Resolves the types of the columns of a table.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a column given a type isn't in the table, or if `invalid_values` is `error` and
+    /// a cell that isn't empty can't be parsed as the type of its column.
+    pub fn build(&self, headers: &[String], data: &[Vec<String>]) -> Result<Schema, String> {
+        let fields = self.types.keys().collect::<Vec<_>>();
+        super::util::validate_fields(headers, &fields)?;
+
+        let types = headers
+            .iter()
+            .map(|header| self.types.get(header).copied())
+            .collect::<Vec<_>>();
+
+        if self.invalid_values == InvalidValues::Error {
+            for (idx, row) in data.iter().enumerate() {
+                for ((cell, header), column_type) in row.iter().zip(headers).zip(&types) {
+                    if let Some(column_type) = column_type {
+                        if !cell.is_empty() && column_type.parse(cell).is_none() {
+                            return Err(format!(
+                                "invalid {} '{}' in column '{}' of row {}",
+                                column_type,
+                                cell,
+                                header,
+                                idx + 1
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Schema { types })
+    }
+}
+
+/// The types of the columns of a table, in the order of its headers.
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    types: Vec<Option<ColumnType>>,
+}
+
+impl Schema {
+    /// Converts the cell in the given column to a value of the column's type. Cells that can't be
+    /// parsed are returned as strings. Empty cells are null, unless the column is untyped or a
+    /// string, so that a missing number isn't mistaken for one.
+    pub fn value(&self, column: usize, cell: &str) -> Value {
+        match self.types.get(column).copied().flatten() {
+            None | Some(ColumnType::String) => cell.into(),
+            Some(_) if cell.is_empty() => Value::Null,
+            Some(column_type) => column_type.parse(cell).unwrap_or_else(|| cell.into()),
+        }
+    }
+
+    /// Returns the value that equality conditions on the given column compare, in place of the
+    /// cell or lookup value. Values of typed columns that parse to the same value are equal, so
+    /// `012` and `12.0` equal `12` in a numeric column. Other values are normalized.
+    pub fn key(&self, column: usize, normalize: &Normalize, value: &str) -> String {
+        let parsed = match self.types.get(column).copied().flatten() {
+            Some(ColumnType::Integer) | Some(ColumnType::Float) => number_key(value.trim()),
+            Some(ColumnType::Boolean) => value
+                .trim()
+                .to_lowercase()
+                .parse::<bool>()
+                .ok()
+                .map(|boolean| boolean.to_string()),
+            Some(ColumnType::Timestamp) => DateTime::parse_from_rfc3339(value.trim())
+                .ok()
+                .map(|timestamp| timestamp.with_timezone(&Utc).to_rfc3339()),
+            None | Some(ColumnType::String) => None,
+        };
+
+        parsed.unwrap_or_else(|| normalize.key(value))
+    }
+}
+
+/// Writes the number in one form, so integers and floats holding a whole number are written the
+/// same way.
+pub(super) fn number_key(value: &str) -> Option<String> {
+    if let Ok(integer) = value.parse::<i64>() {
+        return Some(integer.to_string());
+    }
+
+    let float = value.parse::<f64>().ok().filter(|float| !float.is_nan())?;
+    // Whole numbers within the range where every integer is exactly representable.
+    if float.fract() == 0.0 && float.abs() < 9_007_199_254_740_992.0 {
+        Some((float as i64).to_string())
+    } else {
+        Some(float.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> Vec<String> {
+        vec!["name".to_string(), "count".to_string()]
+    }
+
+    fn config(invalid_values: InvalidValues) -> SchemaConfig {
+        SchemaConfig {
+            types: vec![("count".to_string(), ColumnType::Integer)]
+                .into_iter()
+                .collect(),
+            invalid_values,
+        }
+    }
+
+    #[test]
+    fn parses_types() {
+        assert_eq!(Some(Value::from(12)), ColumnType::Integer.parse("12"));
+        assert_eq!(None, ColumnType::Integer.parse("1.5"));
+        assert_eq!(Some(Value::from(1.5)), ColumnType::Float.parse("1.5"));
+        assert_eq!(None, ColumnType::Float.parse("NaN"));
+        assert_eq!(Some(Value::from(true)), ColumnType::Boolean.parse("true"));
+        assert_eq!(None, ColumnType::Boolean.parse("yes"));
+        assert_eq!(
+            Some(Value::from(
+                DateTime::parse_from_rfc3339("2021-08-01T12:00:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc)
+            )),
+            ColumnType::Timestamp.parse("2021-08-01T12:00:00Z")
+        );
+        assert_eq!(Some(Value::from("12")), ColumnType::String.parse("12"));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let data = vec![
+            vec!["one".to_string(), "1".to_string()],
+            vec!["two".to_string(), "two".to_string()],
+        ];
+
+        assert_eq!(
+            "invalid integer 'two' in column 'count' of row 2",
+            config(InvalidValues::Error)
+                .build(&headers(), &data)
+                .unwrap_err()
+        );
+
+        let schema = config(InvalidValues::KeepString)
+            .build(&headers(), &data)
+            .unwrap();
+        assert_eq!(Value::from(1), schema.value(1, "1"));
+        assert_eq!(Value::from("two"), schema.value(1, "two"));
+        assert_eq!(Value::from("one"), schema.value(0, "one"));
+    }
+
+    #[test]
+    fn keys_parse_typed_values() {
+        let headers = vec![
+            "name".to_string(),
+            "count".to_string(),
+            "ratio".to_string(),
+            "enabled".to_string(),
+            "seen".to_string(),
+        ];
+        let schema = SchemaConfig {
+            types: vec![
+                ("count".to_string(), ColumnType::Integer),
+                ("ratio".to_string(), ColumnType::Float),
+                ("enabled".to_string(), ColumnType::Boolean),
+                ("seen".to_string(), ColumnType::Timestamp),
+            ]
+            .into_iter()
+            .collect(),
+            invalid_values: InvalidValues::KeepString,
+        }
+        .build(&headers, &[])
+        .unwrap();
+        let normalize = Normalize::default();
+        let key = |column: usize, value: &str| schema.key(column, &normalize, value);
+
+        assert_eq!(key(1, "12"), key(1, "012"));
+        assert_eq!(key(1, "12"), key(1, "12.0"));
+        assert_eq!(key(2, "0"), key(2, "-0.0"));
+        assert_eq!(key(2, "1.5"), key(2, "1.50"));
+        assert_ne!(key(2, "1.5"), key(2, "1.25"));
+        assert_eq!(key(3, "true"), key(3, "TRUE"));
+        assert_eq!(
+            key(4, "2021-08-01T12:00:00Z"),
+            key(4, "2021-08-01T14:00:00+02:00")
+        );
+        assert_eq!("twelve", key(1, "Twelve"));
+        assert_eq!("012", key(0, "012"));
+    }
+
+    #[test]
+    fn empty_cells_are_null() {
+        let data = vec![vec!["".to_string(), "".to_string()]];
+
+        let schema = config(InvalidValues::Error)
+            .build(&headers(), &data)
+            .unwrap();
+        assert_eq!(Value::Null, schema.value(1, ""));
+        assert_eq!(Value::from(""), schema.value(0, ""));
+    }
+
+    #[test]
+    fn rejects_unknown_columns() {
+        let config = SchemaConfig {
+            types: vec![("missing".to_string(), ColumnType::Integer)]
+                .into_iter()
+                .collect(),
+            invalid_values: InvalidValues::Error,
+        };
+
+        assert_eq!(
+            "field(s) 'missing' not in table, available columns are: name, count",
+            config.build(&headers(), &[]).unwrap_err()
+        );
+    }
+}
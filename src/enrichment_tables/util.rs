@@ -0,0 +1,322 @@
+//! Helpers shared by the enrichment tables for searching their rows, building and measuring
+//! their indexes, and mapping the indexes of tables wrapping other tables.
+use super::{normalize::Normalize, schema::Schema};
+use crate::internal_events::EnrichmentTableIndexBytes;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use vector_core::enrichment::{Condition, Direction, IndexHandle, IndexKind, Table};
+use vrl::Value;
+
+/// Checks that all the fields are columns of the table.
+///
+/// # Errors
+///
+/// Errors naming the missing fields and listing the available columns.
+pub(super) fn validate_fields<T: AsRef<str>>(
+    columns: &[String],
+    fields: &[T],
+) -> Result<(), String> {
+    let missing = fields
+        .iter()
+        .map(AsRef::<str>::as_ref)
+        .filter(|field| !columns.iter().any(|column| column == field))
+        .map(|field| format!("'{}'", field))
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "field(s) {} not in table, available columns are: {}",
+            missing.join(", "),
+            columns.join(", ")
+        ))
+    }
+}
+
+/// Splits a cell holding a set of values into its members, trimming the whitespace around them.
+/// Empty members are left out, so an empty cell is an empty set.
+pub(super) fn set_members(cell: &str, separator: char) -> impl Iterator<Item = &str> {
+    cell.split(separator)
+        .map(str::trim)
+        .filter(|member| !member.is_empty())
+}
+
+/// Checks if the value is a member of the set in the cell of the column, comparing each member
+/// with the value as `Condition::Equals` compares a cell with a value.
+fn set_contains(
+    schema: &Schema,
+    normalize: &Normalize,
+    column: usize,
+    cell: &str,
+    value: &str,
+    separator: char,
+) -> bool {
+    let value = schema.key(column, normalize, value.trim());
+    set_members(cell, separator).any(|member| schema.key(column, normalize, member) == value)
+}
+
+/// Returns the column the condition is on, or `None` if its field isn't a column of the table.
+pub(super) fn condition_column(headers: &[String], condition: &Condition) -> Option<usize> {
+    let field = match condition {
+        Condition::Equals { field, .. }
+        | Condition::IpInCidr { field, .. }
+        | Condition::Glob { field, .. }
+        | Condition::Contains { field, .. }
+        | Condition::IsEmpty { field }
+        | Condition::IsPresent { field }
+        | Condition::Nearest { field, .. } => field,
+    };
+    headers.iter().position(|header| header == field)
+}
+
+/// Checks the row against the condition. Conditions on fields that aren't columns of the table
+/// never match.
+pub(super) fn condition_matches(
+    headers: &[String],
+    schema: &Schema,
+    normalize: &Normalize,
+    condition: &Condition,
+    row: &[String],
+) -> bool {
+    let column = match condition_column(headers, condition) {
+        Some(column) => column,
+        None => return false,
+    };
+    let cell = &row[column];
+
+    match condition {
+        Condition::Equals { value, .. } => {
+            schema.key(column, normalize, cell) == schema.key(column, normalize, value)
+        }
+        Condition::IpInCidr { ip, .. } => {
+            Network::parse(cell).map_or(false, |network| network.contains(*ip))
+        }
+        Condition::Glob { pattern, .. } => pattern.is_match(cell),
+        Condition::Contains {
+            value, separator, ..
+        } => set_contains(schema, normalize, column, cell, value, *separator),
+        Condition::IsEmpty { .. } => cell.is_empty(),
+        Condition::IsPresent { .. } => !cell.is_empty(),
+        // Only checks the cell is in the right direction, finding the nearest rows is left to the
+        // table.
+        Condition::Nearest {
+            value, direction, ..
+        } => parse_number(cell)
+            .and_then(|number| nearest_rank(*direction, number, *value))
+            .is_some(),
+    }
+}
+
+/// Returns the selected columns of the row, or all of them if there is no selection.
+pub(super) fn row_columns(
+    headers: &[String],
+    schema: &Schema,
+    row: &[String],
+    select: Option<&[String]>,
+) -> BTreeMap<String, Value> {
+    headers
+        .iter()
+        .zip(row)
+        .enumerate()
+        .filter(|(_, (header, _))| select.map_or(true, |select| select.contains(header)))
+        .map(|(idx, (header, col))| (header.clone(), schema.value(idx, col)))
+        .collect()
+}
+
+/// Parses a cell searched by a `Condition::Nearest`.
+pub(super) fn parse_number(cell: &str) -> Option<f64> {
+    cell.parse::<f64>().ok().filter(|number| !number.is_nan())
+}
+
+/// Ranks how close the number is to the value, lower ranks are closer and the lower of two
+/// equally close numbers ranks first. Returns `None` if the number is in the wrong direction.
+pub(super) fn nearest_rank(direction: Direction, number: f64, value: f64) -> Option<(f64, f64)> {
+    match direction {
+        Direction::Nearest if !value.is_nan() => Some(((number - value).abs(), number)),
+        Direction::Floor if number <= value => Some((value - number, number)),
+        Direction::Ceiling if number >= value => Some((number - value, number)),
+        _ => None,
+    }
+}
+
+/// Emits the memory used by the indexes of a table whose data has been swapped or written to,
+/// given the fields of its indexes in the order they were added.
+pub(super) fn emit_index_bytes<'a>(
+    name: &str,
+    fields: impl IntoIterator<Item = &'a Vec<String>>,
+    table: &dyn Table,
+) {
+    let indexes = fields
+        .into_iter()
+        .cloned()
+        .zip(table.index_bytes())
+        .collect::<Vec<_>>();
+    emit!(EnrichmentTableIndexBytes {
+        name,
+        indexes: &indexes,
+    });
+}
+
+/// Maps a handle returned by a table wrapping other tables to the handle of the same index on a
+/// wrapped table, given the wrapped table's handles in the order ours were returned.
+pub(super) fn inner_handle(
+    handles: &[IndexHandle],
+    index: Option<IndexHandle>,
+) -> Option<IndexHandle> {
+    index.and_then(|IndexHandle(handle)| handles.get(handle).copied())
+}
+
+/// The fields and kind of every index requested from a table whose data is swapped for a new
+/// copy, so the indexes can be built again on each copy before it is swapped in.
+#[derive(Clone, Default)]
+pub(super) struct Indexes(Arc<Mutex<Vec<(Vec<String>, IndexKind)>>>);
+
+impl Indexes {
+    /// Builds the indexes requested so far on a new copy of the table, returning the handles in
+    /// the order they were requested.
+    ///
+    /// The returned guard must be held until the copy has been swapped in, so that `add` can't
+    /// record an index that would be missing from it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    pub(super) fn build(
+        &self,
+        table: &mut dyn Table,
+    ) -> Result<
+        (
+            MutexGuard<'_, Vec<(Vec<String>, IndexKind)>>,
+            Vec<IndexHandle>,
+        ),
+        String,
+    > {
+        let indexes = self.0.lock().unwrap();
+        let handles = indexes
+            .iter()
+            .map(|(fields, kind)| {
+                let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+                kind.add_to(table, &fields)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((indexes, handles))
+    }
+
+    /// Records an index once `add` has added it to the current copy of the table. `add` is
+    /// given the position of the new index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Mutex is poisoned.
+    pub(super) fn add<R>(
+        &self,
+        fields: &[&str],
+        kind: IndexKind,
+        add: impl FnOnce(usize) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let mut indexes = self.0.lock().unwrap();
+        let added = add(indexes.len())?;
+        indexes.push((
+            fields.iter().map(|field| (*field).to_string()).collect(),
+            kind,
+        ));
+
+        Ok(added)
+    }
+}
+
+/// An IPv4 or IPv6 network. The address has all bits beyond the prefix cleared.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Network {
+    V4 { address: u32, prefix: u8 },
+    V6 { address: u128, prefix: u8 },
+}
+
+impl Network {
+    /// Parses a network in CIDR notation. A bare IP address is a network containing just that
+    /// address.
+    pub(super) fn parse(network: &str) -> Option<Self> {
+        let network = network.trim();
+        let (address, prefix) = match network.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (network, None),
+        };
+
+        match address.parse::<IpAddr>().ok()? {
+            IpAddr::V4(address) => {
+                let prefix = parse_prefix(prefix, 32)?;
+                Some(Self::V4 {
+                    address: mask_v4(address.into(), prefix),
+                    prefix,
+                })
+            }
+            IpAddr::V6(address) => {
+                let prefix = parse_prefix(prefix, 128)?;
+                Some(Self::V6 {
+                    address: mask_v6(address.into(), prefix),
+                    prefix,
+                })
+            }
+        }
+    }
+
+    pub(super) fn contains(&self, ip: IpAddr) -> bool {
+        match (*self, ip) {
+            (Self::V4 { address, prefix }, IpAddr::V4(ip)) => mask_v4(ip.into(), prefix) == address,
+            (Self::V6 { address, prefix }, IpAddr::V6(ip)) => mask_v6(ip.into(), prefix) == address,
+            _ => false,
+        }
+    }
+}
+
+fn parse_prefix(prefix: Option<&str>, max: u8) -> Option<u8> {
+    match prefix {
+        None => Some(max),
+        Some(prefix) => prefix.parse::<u8>().ok().filter(|prefix| *prefix <= max),
+    }
+}
+
+pub(super) fn mask_v4(address: u32, prefix: u8) -> u32 {
+    if prefix == 0 {
+        0
+    } else {
+        address & (u32::MAX << (32 - u32::from(prefix)))
+    }
+}
+
+pub(super) fn mask_v6(address: u128, prefix: u8) -> u128 {
+    if prefix == 0 {
+        0
+    } else {
+        address & (u128::MAX << (128 - u32::from(prefix)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_networks() {
+        assert_eq!(
+            Some(Network::V4 {
+                address: 0x0a01_0000,
+                prefix: 16
+            }),
+            Network::parse("10.1.2.3/16")
+        );
+        assert_eq!(
+            Some(Network::V4 {
+                address: 0,
+                prefix: 0
+            }),
+            Network::parse("1.2.3.4/0")
+        );
+        assert_eq!(None, Network::parse("10.1.2.3/33"));
+        assert_eq!(None, Network::parse("10.1.2/8"));
+        assert_eq!(None, Network::parse(""));
+    }
+}
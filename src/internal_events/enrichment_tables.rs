@@ -0,0 +1,145 @@
+use super::InternalEvent;
+use metrics::{counter, gauge, histogram};
+use std::time::Duration;
+
+#[derive(Debug)]
+pub struct EnrichmentTableLoaded<'a> {
+    pub name: &'a str,
+    pub elapsed: Duration,
+}
+
+impl InternalEvent for EnrichmentTableLoaded<'_> {
+    fn emit_logs(&self) {
+        info!(
+            message = "Enrichment table loaded.",
+            name = %self.name,
+            elapsed_millis = %self.elapsed.as_millis(),
+        );
+    }
+
+    fn emit_metrics(&self) {
+        histogram!("enrichment_table_load_duration_seconds", self.elapsed);
+    }
+}
+
+#[derive(Debug)]
+pub struct EnrichmentTableLoadFailed<'a> {
+    pub name: &'a str,
+    pub error: crate::Error,
+}
+
+impl InternalEvent for EnrichmentTableLoadFailed<'_> {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to load enrichment table.",
+            name = %self.name,
+            error = %self.error,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("enrichment_table_load_errors_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct EnrichmentTableReloadFailed<'a> {
+    pub name: &'a str,
+    pub error: String,
+}
+
+impl InternalEvent for EnrichmentTableReloadFailed<'_> {
+    fn emit_logs(&self) {
+        error!(
+            message = "Failed to reload enrichment table, serving the current data.",
+            name = %self.name,
+            error = %self.error,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("enrichment_table_reload_errors_total", 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct EnrichmentTableIndexBytes<'a> {
+    pub name: &'a str,
+    /// The fields of each index and the approximate memory it uses in bytes.
+    pub indexes: &'a [(Vec<String>, usize)],
+}
+
+impl InternalEvent for EnrichmentTableIndexBytes<'_> {
+    fn emit_metrics(&self) {
+        for (fields, bytes) in self.indexes {
+            gauge!(
+                "enrichment_index_bytes",
+                *bytes as f64,
+                "table" => self.name.to_string(),
+                "index" => fields.join(","),
+            );
+        }
+        gauge!(
+            "enrichment_table_index_bytes",
+            self.indexes.iter().map(|(_, bytes)| bytes).sum::<usize>() as f64,
+            "table" => self.name.to_string(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::MetricValue;
+    use crate::metrics::{capture_metrics, get_controller};
+
+    #[test]
+    fn emits_index_bytes() {
+        let _ = crate::metrics::init();
+
+        emit!(EnrichmentTableIndexBytes {
+            name: "index_bytes_test",
+            indexes: &[
+                (vec!["host".to_string()], 100),
+                (vec!["host".to_string(), "port".to_string()], 200),
+            ],
+        });
+
+        let controller = get_controller().expect("no controller");
+        let mut gauges = capture_metrics(controller)
+            .filter_map(|metric| {
+                let tags = metric.tags()?;
+                if tags.get("table").map(String::as_str) != Some("index_bytes_test") {
+                    return None;
+                }
+                Some((
+                    metric.name().to_string(),
+                    tags.get("index").cloned(),
+                    metric.value().clone(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        gauges.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+
+        assert_eq!(
+            vec![
+                (
+                    "enrichment_index_bytes".to_string(),
+                    Some("host".to_string()),
+                    MetricValue::Gauge { value: 100.0 }
+                ),
+                (
+                    "enrichment_index_bytes".to_string(),
+                    Some("host,port".to_string()),
+                    MetricValue::Gauge { value: 200.0 }
+                ),
+                (
+                    "enrichment_table_index_bytes".to_string(),
+                    None,
+                    MetricValue::Gauge { value: 300.0 }
+                ),
+            ],
+            gauges
+        );
+    }
+}
@@ -45,6 +45,7 @@ mod dnstap;
 mod docker_logs;
 mod elasticsearch;
 mod encoding_transcode;
+mod enrichment_tables;
 #[cfg(feature = "sources-eventstoredb_metrics")]
 mod eventstoredb_metrics;
 #[cfg(feature = "sources-exec")]
@@ -178,6 +179,7 @@ pub(crate) use self::dnstap::*;
 pub use self::docker_logs::*;
 pub use self::elasticsearch::*;
 pub use self::encoding_transcode::*;
+pub(crate) use self::enrichment_tables::*;
 #[cfg(feature = "sources-eventstoredb_metrics")]
 pub use self::eventstoredb_metrics::*;
 #[cfg(feature = "sources-exec")]
@@ -14,6 +14,7 @@ use glob::{Pattern, PatternError};
 #[cfg(not(target_os = "windows"))]
 use heim::units::ratio::ratio;
 use heim::{units::time::second, Error};
+use rand::Rng;
 use serde::{
     de::{self, Visitor},
     Deserialize, Deserializer, Serialize, Serializer,
@@ -64,6 +65,7 @@ impl Default for Namespace {
 pub struct HostMetricsConfig {
     #[serde(default = "default_scrape_interval")]
     scrape_interval_secs: u64,
+    scrape_jitter_secs: Option<u64>,
 
     collectors: Option<Vec<Collector>>,
     #[serde(default)]
@@ -113,8 +115,8 @@ impl HostMetricsConfig {
         let mut out =
             out.sink_map_err(|error| error!(message = "Error sending host metrics.", %error));
 
-        let duration = time::Duration::from_secs(self.scrape_interval_secs);
-        let mut interval = IntervalStream::new(time::interval(duration)).take_until(shutdown);
+        let mut interval =
+            IntervalStream::new(self.scrape_interval(self.initial_delay())).take_until(shutdown);
         while interval.next().await.is_some() {
             let metrics = self.capture_metrics().await;
             out.send_all(&mut stream::iter(metrics).map(Ok)).await?;
@@ -123,6 +125,23 @@ impl HostMetricsConfig {
         Ok(())
     }
 
+    /// The interval between scrapes, starting with the first scrape after the delay.
+    fn scrape_interval(&self, delay: time::Duration) -> time::Interval {
+        let duration = time::Duration::from_secs(self.scrape_interval_secs);
+        time::interval_at(time::Instant::now() + delay, duration)
+    }
+
+    /// Randomized offset applied before the first scrape so that many instances started at the
+    /// same time don't all hit the host at once.
+    fn initial_delay(&self) -> time::Duration {
+        match self.scrape_jitter_secs {
+            Some(jitter) if jitter > 0 => time::Duration::from_millis(
+                rand::thread_rng().gen_range(0..jitter.saturating_mul(1000)),
+            ),
+            _ => time::Duration::from_secs(0),
+        }
+    }
+
     fn has_collector(&self, collector: Collector) -> bool {
         match &self.collectors {
             None => true,
@@ -469,6 +488,57 @@ pub(self) mod tests {
         assert!(!filters.contains_test(None));
     }
 
+    #[test]
+    fn no_initial_delay_without_jitter() {
+        assert_eq!(
+            HostMetricsConfig::default().initial_delay(),
+            time::Duration::from_secs(0)
+        );
+        assert_eq!(
+            HostMetricsConfig {
+                scrape_jitter_secs: Some(0),
+                ..Default::default()
+            }
+            .initial_delay(),
+            time::Duration::from_secs(0)
+        );
+    }
+
+    #[test]
+    fn initial_delay_within_jitter() {
+        let config = HostMetricsConfig {
+            scrape_jitter_secs: Some(5),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            assert!(config.initial_delay() < time::Duration::from_secs(5));
+        }
+
+        // A bound too large to hold in milliseconds still gives a delay.
+        let config = HostMetricsConfig {
+            scrape_jitter_secs: Some(u64::MAX),
+            ..Default::default()
+        };
+        config.initial_delay();
+    }
+
+    #[tokio::test]
+    async fn first_scrape_is_delayed() {
+        time::pause();
+        let config = HostMetricsConfig {
+            scrape_interval_secs: 10,
+            ..Default::default()
+        };
+
+        let start = time::Instant::now();
+        let mut interval = config.scrape_interval(time::Duration::from_secs(3));
+        interval.tick().await;
+        assert_eq!(time::Duration::from_secs(3), start.elapsed());
+
+        interval.tick().await;
+        assert_eq!(time::Duration::from_secs(13), start.elapsed());
+    }
+
     #[tokio::test]
     async fn filters_on_collectors() {
         let all_metrics_count = HostMetricsConfig::default().capture_metrics().await.count();
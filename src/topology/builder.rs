@@ -7,7 +7,7 @@ use crate::{
     buffers,
     config::{ComponentId, DataType, ProxyConfig, SinkContext, SourceContext, TransformContext},
     event::Event,
-    internal_events::{EventIn, EventOut},
+    internal_events::{EnrichmentTableIndexBytes, EnrichmentTableReloadFailed, EventIn, EventOut},
     shutdown::SourceShutdownCoordinator,
     transforms::Transform,
     Pipeline,
@@ -25,7 +25,8 @@ use tokio::time::{timeout, Duration};
 use vector_core::enrichment;
 
 lazy_static! {
-    static ref ENRICHMENT_TABLES: enrichment::TableRegistry = enrichment::TableRegistry::default();
+    pub(crate) static ref ENRICHMENT_TABLES: enrichment::TableRegistry =
+        enrichment::TableRegistry::default();
 }
 
 pub struct Pieces {
@@ -56,21 +57,38 @@ pub async fn build_pieces(
     let mut errors = vec![];
 
     let mut enrichment_tables = HashMap::new();
+    let mut reloaded_tables = HashMap::new();
+
+    // Build enrichment tables. Tables whose config has changed are built again, as are tables
+    // that re-read their data on every reload, and replace the copy being served once the config
+    // has reloaded. A table that fails to build again keeps serving its current data.
+    for (name, table) in config.enrichment_tables.iter() {
+        let reload = if diff.enrichment_tables.to_add.contains(name) {
+            false
+        } else if diff.enrichment_tables.to_change.contains(name) || table.inner.reloads_data() {
+            true
+        } else {
+            continue;
+        };
 
-    // Build enrichment tables
-    for (name, table) in config
-        .enrichment_tables
-        .iter()
-        .filter(|(name, _)| diff.enrichment_tables.contains_new(name))
-    {
-        let table = match table.inner.build(&config.global).await {
-            Ok(table) => table,
-            Err(error) => {
-                errors.push(format!("Enrichment Table \"{}\": {}", name, error));
-                continue;
+        if reload {
+            match table.inner.rebuild(name.as_str(), &config.global).await {
+                Ok(table) => {
+                    reloaded_tables.insert(name.as_str().to_string(), table);
+                }
+                Err(error) => emit!(EnrichmentTableReloadFailed {
+                    name: name.as_str(),
+                    error: error.to_string(),
+                }),
             }
-        };
-        enrichment_tables.insert(name.as_str().to_string(), table);
+        } else {
+            match table.inner.build(name.as_str(), &config.global).await {
+                Ok(table) => {
+                    enrichment_tables.insert(name.as_str().to_string(), table);
+                }
+                Err(error) => errors.push(format!("Enrichment Table \"{}\": {}", name, error)),
+            }
+        }
     }
 
     // Build sources
@@ -128,6 +146,11 @@ pub async fn build_pieces(
     }
 
     ENRICHMENT_TABLES.load(enrichment_tables);
+    for (name, table) in reloaded_tables {
+        if let Err(error) = ENRICHMENT_TABLES.reload(&name, table) {
+            emit!(EnrichmentTableReloadFailed { name: &name, error });
+        }
+    }
 
     let context = TransformContext {
         globals: config.global.clone(),
@@ -321,9 +344,35 @@ pub async fn build_pieces(
         detach_triggers.insert(id.clone(), trigger);
     }
 
+    // Tables removed from the config are only dropped once the new config is known to be good.
+    if errors.is_empty() {
+        let index_bytes = ENRICHMENT_TABLES.index_bytes();
+        for name in &diff.enrichment_tables.to_remove {
+            ENRICHMENT_TABLES.remove(name.as_str());
+
+            // Zero the gauges of the removed table so they don't keep reporting its indexes.
+            if let Some(indexes) = index_bytes.get(name.as_str()) {
+                let indexes = indexes
+                    .iter()
+                    .map(|(fields, _)| (fields.clone(), 0))
+                    .collect::<Vec<_>>();
+                emit!(EnrichmentTableIndexBytes {
+                    name: name.as_str(),
+                    indexes: &indexes,
+                });
+            }
+        }
+    }
+
     // We should have all the data for the enrichment tables loaded now, so switch them over to
     // readonly.
     ENRICHMENT_TABLES.finish_load();
+    for (name, indexes) in ENRICHMENT_TABLES.index_bytes() {
+        emit!(EnrichmentTableIndexBytes {
+            name: &name,
+            indexes: &indexes,
+        });
+    }
 
     if errors.is_empty() {
         let pieces = Pieces {
@@ -116,6 +116,15 @@ components: sources: host_metrics: {
 				unit:    "seconds"
 			}
 		}
+		scrape_jitter_secs: {
+			description: "The upper bound of a random delay applied before the first metric gathering, in seconds. Spreads the load when many instances start at the same time."
+			common:      false
+			required:    false
+			type: uint: {
+				default: null
+				unit:    "seconds"
+			}
+		}
 		disk: {
 			common:      false
 			description: #"Options for the "disk" metrics collector."#